@@ -0,0 +1,75 @@
+// This is a generated file! Please edit source .ksy file and use kaitai-struct-compiler to rebuild
+
+#[allow(unused_imports)]
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[allow(irrefutable_let_patterns)]
+#[allow(unused_comparisons)]
+#[allow(arithmetic_overflow)]
+#[allow(overflowing_literals)]
+
+extern crate kaitai;
+use kaitai::*;
+use std::convert::{TryFrom, TryInto};
+use std::cell::{Ref, Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+/**
+ * \sa https://github.com/libyal/libewf/blob/main/documentation/Expert%20Witness%20Compression%20Format%20(EWF).asciidoc#error2-header Source
+ */
+
+#[derive(Default, Debug, Clone)]
+pub struct EwfError2Header {
+    pub _root: SharedType<EwfError2Header>,
+    pub _parent: SharedType<EwfError2Header>,
+    pub _self: SharedType<Self>,
+    number_of_entries: RefCell<u32>,
+    unknown1: RefCell<Vec<u8>>,
+    checksum: RefCell<u32>,
+    _io: RefCell<BytesReader>,
+}
+impl KStruct for EwfError2Header {
+    type Root = EwfError2Header;
+    type Parent = EwfError2Header;
+
+    fn read<S: KStream>(
+        self_rc: &OptRc<Self>,
+        _io: &S,
+        _root: SharedType<Self::Root>,
+        _parent: SharedType<Self::Parent>,
+    ) -> KResult<()> {
+        *self_rc._io.borrow_mut() = _io.clone();
+        self_rc._root.set(_root.get());
+        self_rc._parent.set(_parent.get());
+        self_rc._self.set(Ok(self_rc.clone()));
+        let _rrc = self_rc._root.get_value().borrow().upgrade();
+        let _prc = self_rc._parent.get_value().borrow().upgrade();
+        let _r = _rrc.as_ref().unwrap();
+        *self_rc.number_of_entries.borrow_mut() = _io.read_u4le()?.into();
+        *self_rc.unknown1.borrow_mut() = _io.read_bytes(512 as usize)?.into();
+        *self_rc.checksum.borrow_mut() = _io.read_u4le()?.into();
+        Ok(())
+    }
+}
+impl EwfError2Header {
+}
+impl EwfError2Header {
+    pub fn number_of_entries(&self) -> Ref<'_, u32> {
+        self.number_of_entries.borrow()
+    }
+}
+impl EwfError2Header {
+    pub fn unknown1(&self) -> Ref<'_, Vec<u8>> {
+        self.unknown1.borrow()
+    }
+}
+impl EwfError2Header {
+    pub fn checksum(&self) -> Ref<'_, u32> {
+        self.checksum.borrow()
+    }
+}
+impl EwfError2Header {
+    pub fn _io(&self) -> Ref<'_, BytesReader> {
+        self._io.borrow()
+    }
+}
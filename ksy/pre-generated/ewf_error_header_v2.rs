@@ -0,0 +1,82 @@
+// This is a generated file! Please edit source .ksy file and use kaitai-struct-compiler to rebuild
+
+#[allow(unused_imports)]
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[allow(irrefutable_let_patterns)]
+#[allow(unused_comparisons)]
+#[allow(arithmetic_overflow)]
+#[allow(overflowing_literals)]
+
+extern crate kaitai;
+use kaitai::*;
+use std::convert::{TryFrom, TryInto};
+use std::cell::{Ref, Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+/**
+ * \sa https://github.com/libyal/libewf/blob/main/documentation/Expert%20Witness%20Compression%20Format%202%20(EWF2).asciidoc#error-table Source
+ */
+
+#[derive(Default, Debug, Clone)]
+pub struct EwfErrorHeaderV2 {
+    pub _root: SharedType<EwfErrorHeaderV2>,
+    pub _parent: SharedType<EwfErrorHeaderV2>,
+    pub _self: SharedType<Self>,
+    number_of_entries: RefCell<u32>,
+    padding1: RefCell<Vec<u8>>,
+    checksum: RefCell<u32>,
+    padding2: RefCell<Vec<u8>>,
+    _io: RefCell<BytesReader>,
+}
+impl KStruct for EwfErrorHeaderV2 {
+    type Root = EwfErrorHeaderV2;
+    type Parent = EwfErrorHeaderV2;
+
+    fn read<S: KStream>(
+        self_rc: &OptRc<Self>,
+        _io: &S,
+        _root: SharedType<Self::Root>,
+        _parent: SharedType<Self::Parent>,
+    ) -> KResult<()> {
+        *self_rc._io.borrow_mut() = _io.clone();
+        self_rc._root.set(_root.get());
+        self_rc._parent.set(_parent.get());
+        self_rc._self.set(Ok(self_rc.clone()));
+        let _rrc = self_rc._root.get_value().borrow().upgrade();
+        let _prc = self_rc._parent.get_value().borrow().upgrade();
+        let _r = _rrc.as_ref().unwrap();
+        *self_rc.number_of_entries.borrow_mut() = _io.read_u4le()?.into();
+        *self_rc.padding1.borrow_mut() = _io.read_bytes(12 as usize)?.into();
+        *self_rc.checksum.borrow_mut() = _io.read_u4le()?.into();
+        *self_rc.padding2.borrow_mut() = _io.read_bytes(12 as usize)?.into();
+        Ok(())
+    }
+}
+impl EwfErrorHeaderV2 {
+}
+impl EwfErrorHeaderV2 {
+    pub fn number_of_entries(&self) -> Ref<'_, u32> {
+        self.number_of_entries.borrow()
+    }
+}
+impl EwfErrorHeaderV2 {
+    pub fn padding1(&self) -> Ref<'_, Vec<u8>> {
+        self.padding1.borrow()
+    }
+}
+impl EwfErrorHeaderV2 {
+    pub fn checksum(&self) -> Ref<'_, u32> {
+        self.checksum.borrow()
+    }
+}
+impl EwfErrorHeaderV2 {
+    pub fn padding2(&self) -> Ref<'_, Vec<u8>> {
+        self.padding2.borrow()
+    }
+}
+impl EwfErrorHeaderV2 {
+    pub fn _io(&self) -> Ref<'_, BytesReader> {
+        self._io.borrow()
+    }
+}
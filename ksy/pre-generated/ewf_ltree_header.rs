@@ -0,0 +1,82 @@
+// This is a generated file! Please edit source .ksy file and use kaitai-struct-compiler to rebuild
+
+#[allow(unused_imports)]
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[allow(irrefutable_let_patterns)]
+#[allow(unused_comparisons)]
+#[allow(arithmetic_overflow)]
+#[allow(overflowing_literals)]
+
+extern crate kaitai;
+use kaitai::*;
+use std::convert::{TryFrom, TryInto};
+use std::cell::{Ref, Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+/**
+ * \sa https://github.com/libyal/libewf/blob/main/documentation/Expert%20Witness%20Compression%20Format%20(EWF).asciidoc#ltree-section Source
+ */
+
+#[derive(Default, Debug, Clone)]
+pub struct EwfLtreeHeader {
+    pub _root: SharedType<EwfLtreeHeader>,
+    pub _parent: SharedType<EwfLtreeHeader>,
+    pub _self: SharedType<Self>,
+    integrity_hash: RefCell<Vec<u8>>,
+    data_size: RefCell<u64>,
+    checksum: RefCell<u32>,
+    unknown1: RefCell<Vec<u8>>,
+    _io: RefCell<BytesReader>,
+}
+impl KStruct for EwfLtreeHeader {
+    type Root = EwfLtreeHeader;
+    type Parent = EwfLtreeHeader;
+
+    fn read<S: KStream>(
+        self_rc: &OptRc<Self>,
+        _io: &S,
+        _root: SharedType<Self::Root>,
+        _parent: SharedType<Self::Parent>,
+    ) -> KResult<()> {
+        *self_rc._io.borrow_mut() = _io.clone();
+        self_rc._root.set(_root.get());
+        self_rc._parent.set(_parent.get());
+        self_rc._self.set(Ok(self_rc.clone()));
+        let _rrc = self_rc._root.get_value().borrow().upgrade();
+        let _prc = self_rc._parent.get_value().borrow().upgrade();
+        let _r = _rrc.as_ref().unwrap();
+        *self_rc.integrity_hash.borrow_mut() = _io.read_bytes(16 as usize)?.into();
+        *self_rc.data_size.borrow_mut() = _io.read_u8le()?.into();
+        *self_rc.checksum.borrow_mut() = _io.read_u4le()?.into();
+        *self_rc.unknown1.borrow_mut() = _io.read_bytes(20 as usize)?.into();
+        Ok(())
+    }
+}
+impl EwfLtreeHeader {
+}
+impl EwfLtreeHeader {
+    pub fn integrity_hash(&self) -> Ref<'_, Vec<u8>> {
+        self.integrity_hash.borrow()
+    }
+}
+impl EwfLtreeHeader {
+    pub fn data_size(&self) -> Ref<'_, u64> {
+        self.data_size.borrow()
+    }
+}
+impl EwfLtreeHeader {
+    pub fn checksum(&self) -> Ref<'_, u32> {
+        self.checksum.borrow()
+    }
+}
+impl EwfLtreeHeader {
+    pub fn unknown1(&self) -> Ref<'_, Vec<u8>> {
+        self.unknown1.borrow()
+    }
+}
+impl EwfLtreeHeader {
+    pub fn _io(&self) -> Ref<'_, BytesReader> {
+        self._io.borrow()
+    }
+}
@@ -0,0 +1,89 @@
+// This is a generated file! Please edit source .ksy file and use kaitai-struct-compiler to rebuild
+
+#[allow(unused_imports)]
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[allow(irrefutable_let_patterns)]
+#[allow(unused_comparisons)]
+#[allow(arithmetic_overflow)]
+#[allow(overflowing_literals)]
+
+extern crate kaitai;
+use kaitai::*;
+use std::convert::{TryFrom, TryInto};
+use std::cell::{Ref, Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+/**
+ * \sa https://github.com/libyal/libewf/blob/main/documentation/Expert%20Witness%20Compression%20Format%202%20(EWF2).asciidoc#sector-table Source
+ */
+
+#[derive(Default, Debug, Clone)]
+pub struct EwfTableHeaderV2 {
+    pub _root: SharedType<EwfTableHeaderV2>,
+    pub _parent: SharedType<EwfTableHeaderV2>,
+    pub _self: SharedType<Self>,
+    first_chunk_number: RefCell<u64>,
+    entry_count: RefCell<u32>,
+    padding1: RefCell<Vec<u8>>,
+    checksum: RefCell<u32>,
+    padding2: RefCell<Vec<u8>>,
+    _io: RefCell<BytesReader>,
+}
+impl KStruct for EwfTableHeaderV2 {
+    type Root = EwfTableHeaderV2;
+    type Parent = EwfTableHeaderV2;
+
+    fn read<S: KStream>(
+        self_rc: &OptRc<Self>,
+        _io: &S,
+        _root: SharedType<Self::Root>,
+        _parent: SharedType<Self::Parent>,
+    ) -> KResult<()> {
+        *self_rc._io.borrow_mut() = _io.clone();
+        self_rc._root.set(_root.get());
+        self_rc._parent.set(_parent.get());
+        self_rc._self.set(Ok(self_rc.clone()));
+        let _rrc = self_rc._root.get_value().borrow().upgrade();
+        let _prc = self_rc._parent.get_value().borrow().upgrade();
+        let _r = _rrc.as_ref().unwrap();
+        *self_rc.first_chunk_number.borrow_mut() = _io.read_u8le()?.into();
+        *self_rc.entry_count.borrow_mut() = _io.read_u4le()?.into();
+        *self_rc.padding1.borrow_mut() = _io.read_bytes(4 as usize)?.into();
+        *self_rc.checksum.borrow_mut() = _io.read_u4le()?.into();
+        *self_rc.padding2.borrow_mut() = _io.read_bytes(12 as usize)?.into();
+        Ok(())
+    }
+}
+impl EwfTableHeaderV2 {
+}
+impl EwfTableHeaderV2 {
+    pub fn first_chunk_number(&self) -> Ref<'_, u64> {
+        self.first_chunk_number.borrow()
+    }
+}
+impl EwfTableHeaderV2 {
+    pub fn entry_count(&self) -> Ref<'_, u32> {
+        self.entry_count.borrow()
+    }
+}
+impl EwfTableHeaderV2 {
+    pub fn padding1(&self) -> Ref<'_, Vec<u8>> {
+        self.padding1.borrow()
+    }
+}
+impl EwfTableHeaderV2 {
+    pub fn checksum(&self) -> Ref<'_, u32> {
+        self.checksum.borrow()
+    }
+}
+impl EwfTableHeaderV2 {
+    pub fn padding2(&self) -> Ref<'_, Vec<u8>> {
+        self.padding2.borrow()
+    }
+}
+impl EwfTableHeaderV2 {
+    pub fn _io(&self) -> Ref<'_, BytesReader> {
+        self._io.borrow()
+    }
+}
@@ -0,0 +1,165 @@
+use futures::future::{Future, TryFutureExt, try_join_all};
+use tracing::trace;
+
+use crate::bytessource::BytesSource;
+
+// Groups the offsets of blocks to fetch, which must be ascending, into
+// [beg, end) ranges of adjacent blocks, none longer than max_len unless a
+// single block is, so that each range can be fetched with one request.
+pub fn coalesce_blocks(
+    offsets: &[u64],
+    chlen: u64,
+    max_len: u64,
+    end: u64
+) -> Vec<(u64, u64)>
+{
+    let mut ranges: Vec<(u64, u64)> = vec![];
+
+    for &off in offsets {
+        let bend = (off + chlen).min(end);
+        match ranges.last_mut() {
+            Some((rbeg, rend)) if *rend == off && bend - *rbeg <= max_len =>
+                *rend = bend,
+            _ => ranges.push((off, bend))
+        }
+    }
+
+    ranges
+}
+
+// Reads the given ranges from the source and splits them into blocks, as
+// (offset, bytes) pairs. The returned future does not borrow the source.
+pub fn fetch_blocks(
+    source: &(dyn BytesSource + Send),
+    ranges: &[(u64, u64)],
+    chlen: u64
+) -> impl Future<Output = Result<Vec<(u64, Vec<u8>)>, std::io::Error>> + Send + 'static
+{
+    let reads = ranges.iter()
+        .map(|&(beg, end)| {
+            trace!("fetching [{beg},{end})");
+            source.read(beg, end)
+                .map_ok(move |buf| buf.chunks(chlen as usize)
+                    .zip((beg..).step_by(chlen as usize))
+                    .map(|(b, off)| (off, b.to_vec()))
+                    .collect::<Vec<_>>()
+                )
+        })
+        .collect::<Vec<_>>();
+
+    try_join_all(reads)
+        .map_ok(|ranges| ranges.into_iter().flatten().collect())
+}
+
+// Returns the range to read ahead after a read ending at next, given the
+// read-ahead length and the end of the read-ahead requested so far. More is
+// requested only once half of it has been read, so that it is requested in
+// batches; a read outside it starts anew.
+pub fn readahead_range(
+    next: u64,
+    len: u64,
+    requested: u64,
+    end: u64
+) -> Option<(u64, u64)>
+{
+    let raend = (next + len).min(end);
+
+    let rabeg = if (next..=raend).contains(&requested) {
+        if requested - next >= len / 2 {
+            return None;
+        }
+        requested
+    }
+    else {
+        next
+    };
+
+    (rabeg < raend).then_some((rabeg, raend))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_helper::CountingSource;
+
+    #[test]
+    fn coalesce_blocks_adjacent() {
+        assert_eq!(
+            coalesce_blocks(&[0, 10, 20, 40, 50, 70], 10, 100, 75),
+            [(0, 30), (40, 60), (70, 75)]
+        );
+    }
+
+    #[test]
+    fn coalesce_blocks_capped() {
+        assert_eq!(
+            coalesce_blocks(&[0, 10, 20, 30, 40], 10, 20, 1000),
+            [(0, 20), (20, 40), (40, 50)]
+        );
+    }
+
+    #[test]
+    fn coalesce_blocks_cap_below_block() {
+        assert_eq!(
+            coalesce_blocks(&[0, 10], 10, 5, 1000),
+            [(0, 10), (10, 20)]
+        );
+    }
+
+    #[test]
+    fn coalesce_blocks_none() {
+        assert_eq!(coalesce_blocks(&[], 10, 100, 1000), []);
+    }
+
+    #[test]
+    fn readahead_range_batches() {
+        // nothing requested yet
+        assert_eq!(readahead_range(10, 40, 0, 1000), Some((10, 50)));
+        // more than half left
+        assert_eq!(readahead_range(20, 40, 50, 1000), None);
+        assert_eq!(readahead_range(30, 40, 50, 1000), None);
+        // half or less left
+        assert_eq!(readahead_range(40, 40, 50, 1000), Some((50, 80)));
+        // at the end
+        assert_eq!(readahead_range(990, 40, 0, 1000), Some((990, 1000)));
+        assert_eq!(readahead_range(1000, 40, 1000, 1000), None);
+    }
+
+    #[test]
+    fn readahead_range_seek() {
+        // backwards
+        assert_eq!(readahead_range(10, 40, 500, 1000), Some((10, 50)));
+        // forwards
+        assert_eq!(readahead_range(600, 40, 50, 1000), Some((600, 640)));
+    }
+
+    #[test]
+    fn readahead_range_none() {
+        assert_eq!(readahead_range(10, 0, 0, 1000), None);
+    }
+
+    #[test]
+    fn fetch_blocks_split() {
+        let src = CountingSource::new(75);
+
+        let ranges = coalesce_blocks(&[0, 10, 20, 40, 70], 10, 100, 75);
+        let blocks = futures::executor::block_on(
+            fetch_blocks(&src, &ranges, 10)
+        ).unwrap();
+
+        assert_eq!(
+            *src.requests.lock().unwrap(),
+            [(0, 30), (40, 50), (70, 75)]
+        );
+
+        assert_eq!(
+            blocks.iter().map(|(off, b)| (*off, b.len())).collect::<Vec<_>>(),
+            [(0, 10), (10, 10), (20, 10), (40, 10), (70, 5)]
+        );
+
+        for (off, b) in blocks {
+            assert_eq!(b[0], off as u8);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::future::Future;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+// Runs a future on the given multi-threaded runtime from synchronous code.
+// Unlike Handle::block_on, this may be called from within async code, where
+// it blocks the calling thread, first moving the runtime's other tasks off
+// it if it is a worker thread.
+pub(crate) fn block_on<F: Future>(runtime: &Handle, f: F) -> F::Output {
+    match Handle::try_current().map(|h| h.runtime_flavor()) {
+        // not in async code, so we may block on the runtime directly
+        Err(_) => runtime.block_on(f),
+        Ok(RuntimeFlavor::MultiThread) => tokio::task::block_in_place(
+            || runtime.block_on(f)
+        ),
+        // a current-thread runtime can't give up its thread, but ours is
+        // driven by its own threads, so we need only wait
+        Ok(_) => {
+            let _guard = runtime.enter();
+            futures::executor::block_on(f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn block_on_outside_runtime() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        assert_eq!(block_on(rt.handle(), async { 1 }), 1);
+    }
+
+    #[test]
+    fn block_on_inside_same_runtime() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let h = rt.handle().clone();
+        let r = rt.block_on(async move {
+            tokio::task::spawn(async move {
+                block_on(&h, async {
+                    tokio::task::spawn(async { 1 }).await.unwrap()
+                })
+            })
+            .await
+            .unwrap()
+        });
+        assert_eq!(r, 1);
+    }
+
+    #[test]
+    fn block_on_inside_current_thread_runtime() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let h = rt.handle().clone();
+        let caller = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let r = caller.block_on(async move {
+            block_on(&h, async {
+                tokio::task::spawn(async { 1 }).await.unwrap()
+            })
+        });
+        assert_eq!(r, 1);
+    }
+}
@@ -0,0 +1,23 @@
+use crate::workersource::WorkerSource;
+
+// A WorkerSource over chunk data which has already been fetched
+pub struct BufWorkerSource {
+    pub off: u64,
+    pub buf: Vec<u8>
+}
+
+impl WorkerSource for BufWorkerSource {
+    fn read(
+        &mut self,
+        off: u64,
+        buf: &mut [u8]
+    ) -> Result<(), std::io::Error>
+    {
+        let src = off.checked_sub(self.off)
+            .and_then(|beg| self.buf.get(beg as usize..)?.get(..buf.len()))
+            .ok_or(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?;
+
+        buf.copy_from_slice(src);
+        Ok(())
+    }
+}
@@ -1,11 +1,67 @@
 use futures::future::BoxFuture;
 
+// A source of segment bytes, e.g., a file or an object in S3. Implement
+// this to read segments from other storage with E01Reader::open_sources.
 pub trait BytesSource {
+    // Returns bytes [beg, end) of the segment
     fn read(
         &self,
         beg: u64,
         end: u64
     ) -> BoxFuture<'static, Result<Vec<u8>, std::io::Error>>;
 
+    // Returns the length of the segment
     fn end(&self) -> u64;
+
+    // Returns a token which changes when the segment does, e.g., its
+    // modification time or ETag, so that a persistent cache can tell when
+    // its blocks are stale; None if there is none, and then the segment's
+    // blocks are cached for this process only
+    fn version(&self) -> Option<String> {
+        None
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct TransientError(Box<dyn std::error::Error + Send + Sync>);
+
+// Returns an error for a read which may succeed if retried, e.g., one
+// refused with HTTP 503. Sources should return other errors, e.g., for
+// missing objects, as they are, so that they fail at once.
+pub fn transient_error<E>(e: E) -> std::io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>
+{
+    std::io::Error::other(TransientError(e.into()))
+}
+
+// Returns whether a read which failed with the error may succeed if retried
+pub fn is_transient(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind::*;
+
+    matches!(
+        e.kind(),
+        TimedOut | ConnectionReset | ConnectionAborted | BrokenPipe |
+        UnexpectedEof | Interrupted
+    ) || e.get_ref().is_some_and(|e| e.is::<TransientError>())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn is_transient_ok() {
+        assert!(is_transient(&transient_error("503 Slow Down")));
+        assert!(is_transient(&std::io::ErrorKind::TimedOut.into()));
+        assert!(is_transient(&std::io::ErrorKind::ConnectionReset.into()));
+    }
+
+    #[test]
+    fn is_transient_not() {
+        assert!(!is_transient(&std::io::Error::other("404 Not Found")));
+        assert!(!is_transient(&std::io::ErrorKind::NotFound.into()));
+        assert!(!is_transient(&std::io::ErrorKind::PermissionDenied.into()));
+    }
 }
@@ -2,10 +2,12 @@ use async_trait::async_trait;
 
 use crate::bytessource::BytesSource;
 
+// Reads may be made concurrently, so implementations lock any state they
+// need themselves.
 #[async_trait]
 pub trait Cache {
     async fn read(
-        &mut self,
+        &self,
         idx: usize,
         off: u64,
         buf: &mut [u8]
@@ -13,5 +15,11 @@ pub trait Cache {
 
     fn end(&self, idx: usize) -> Result<u64, std::io::Error>;
 
-    fn add_source(&mut self, idx: usize, src: Box<dyn BytesSource + Send>);
+    // name identifies the source, e.g., its path or URL
+    fn add_source(
+        &self,
+        idx: usize,
+        name: &str,
+        src: Box<dyn BytesSource + Send>
+    );
 }
@@ -1,14 +1,17 @@
 use std::{
     io::{Read, Seek, SeekFrom},
-    sync::{Arc, Mutex}
+    sync::Arc
 };
-use tokio::runtime::Runtime;
+use tokio::runtime::Handle;
 
-use crate::cache::Cache;
+use crate::{
+    blocking::block_on,
+    cache::Cache
+};
 
 pub struct CacheReadSeek {
-    cache: Arc<Mutex<dyn Cache + Send>>,
-    runtime: Arc<Runtime>,
+    cache: Arc<dyn Cache + Send + Sync>,
+    runtime: Handle,
     idx: usize,
     pos: u64
 }
@@ -16,8 +19,8 @@ pub struct CacheReadSeek {
 impl CacheReadSeek
 {
     pub fn new(
-        cache: Arc<Mutex<dyn Cache + Send>>,
-        runtime: Arc<Runtime>,
+        cache: Arc<dyn Cache + Send + Sync>,
+        runtime: Handle,
         idx: usize,
         _len: u64
     ) -> Self {
@@ -36,8 +39,7 @@ impl Read for CacheReadSeek {
         buf: &mut [u8]
     ) -> Result<usize, std::io::Error>
     {
-        let mut cache = self.cache.lock().expect("poisoned");
-        self.runtime.block_on(cache.read(self.idx, self.pos, buf))?;
+        block_on(&self.runtime, self.cache.read(self.idx, self.pos, buf))?;
 
         self.pos += buf.len() as u64;
         Ok(buf.len())
@@ -50,7 +52,7 @@ impl Seek for CacheReadSeek {
         pos: SeekFrom
     ) -> Result<u64, std::io::Error>
     {
-        let end = self.cache.lock().expect("poisoned").end(self.idx)?;
+        let end = self.cache.end(self.idx)?;
 
         let (base, offset) = match pos {
             SeekFrom::Start(n) => (n, 0),
@@ -1,12 +1,13 @@
-use std::sync::{Arc, Mutex};
-use tokio::runtime::Runtime;
+use std::sync::Arc;
+use tokio::runtime::Handle;
 
+use crate::blocking::block_on;
 use crate::cache::Cache;
 use crate::workersource::WorkerSource;
 
 pub struct CacheWorkerSource {
-    pub cache: Arc<Mutex<dyn Cache + Send>>,
-    pub runtime: Arc<Runtime>,
+    pub cache: Arc<dyn Cache + Send + Sync>,
+    pub runtime: Handle,
     pub idx: usize
 }
 
@@ -17,7 +18,6 @@ impl WorkerSource for CacheWorkerSource {
         buf: &mut [u8]
     ) -> Result<(), std::io::Error>
     {
-        let mut cache = self.cache.lock().unwrap();
-        self.runtime.block_on(cache.read(self.idx, off, buf))
+        block_on(&self.runtime, self.cache.read(self.idx, off, buf))
     }
 }
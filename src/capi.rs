@@ -81,7 +81,13 @@ impl From<E01ReaderOptions> for e01_reader::E01ReaderOptions {
     fn from(options: E01ReaderOptions) -> e01_reader::E01ReaderOptions {
         e01_reader::E01ReaderOptions {
             corrupt_section_policy: options.corrupt_section_policy.into(),
-            corrupt_chunk_policy: options.corrupt_chunk_policy.into()
+            corrupt_chunk_policy: options.corrupt_chunk_policy.into(),
+            runtime: None,
+            s3: None,
+            retry: None,
+            cache: None,
+            read_parallelism: None,
+            max_open_files: None
         }
     }
 }
@@ -0,0 +1,359 @@
+use tracing::debug;
+
+use crate::{
+    error::LibError,
+    xmlsection::parse_flat_xml
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HeaderDate {
+    // seconds since the Unix epoch, as found in header2 sections
+    Unix(i64),
+    // year, month, day, hour, minute, second in the local time of the
+    // acquiring machine, as found in header sections
+    Local(u16, u8, u8, u8, u8, u8),
+    // free-form date, as found in xheader sections
+    Text(String)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CaseMetadata {
+    pub case_number: Option<String>,
+    pub evidence_number: Option<String>,
+    pub description: Option<String>,
+    pub examiner_name: Option<String>,
+    pub notes: Option<String>,
+    pub model: Option<String>,
+    pub serial_number: Option<String>,
+    pub device_label: Option<String>,
+    pub acquisition_software_version: Option<String>,
+    pub acquisition_os: Option<String>,
+    pub acquisition_date: Option<HeaderDate>,
+    pub system_date: Option<HeaderDate>,
+    pub password_hash: Option<String>,
+    pub compression_level: Option<String>,
+    // key-value pairs with keys we don't recognize, in the order found
+    pub other: Vec<(String, String)>
+}
+
+fn parse_date(v: &str) -> Option<HeaderDate> {
+    let parts = v.split_whitespace().collect::<Vec<_>>();
+
+    match parts[..] {
+        [ts] => ts.parse().ok().map(HeaderDate::Unix),
+        [y, mo, d, h, mi, s] => Some(HeaderDate::Local(
+            y.parse().ok()?,
+            mo.parse().ok()?,
+            d.parse().ok()?,
+            h.parse().ok()?,
+            mi.parse().ok()?,
+            s.parse().ok()?
+        )),
+        _ => None
+    }
+}
+
+// header, header2, and the EWF2 device information and case data sections
+// are tab-separated tables, the first of which is the "main" category
+pub(crate) fn main_category(
+    section_type: &'static str,
+    text: &str
+) -> Result<Vec<(String, String)>, LibError>
+{
+    let malformed = |m: &str| LibError::MalformedSection(
+        section_type,
+        m.into()
+    );
+
+    let mut lines = text.lines();
+
+    // the first line is the number of categories, the second is the
+    // name of the first category, which must be "main"
+    let _category_count = lines.next()
+        .ok_or_else(|| malformed("missing category count"))?;
+
+    match lines.next() {
+        Some("main") => {},
+        Some(c) => return Err(malformed(&format!("expected main category, found {c}"))),
+        None => return Err(malformed("missing main category"))
+    }
+
+    let keys = lines.next()
+        .ok_or_else(|| malformed("missing main category keys"))?
+        .split('\t');
+
+    let values = lines.next()
+        .ok_or_else(|| malformed("missing main category values"))?
+        .split('\t');
+
+    Ok(
+        keys.zip(values)
+            .inspect(|(k, v)| debug!("{section_type} {k} = {v}"))
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect()
+    )
+}
+
+pub(crate) fn decode_utf16le(data: &[u8]) -> String {
+    // skip the byte order mark, if any
+    let data = data.strip_prefix(&[0xff, 0xfe]).unwrap_or(data);
+
+    char::decode_utf16(
+        data.chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+    )
+    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+    .collect()
+}
+
+impl CaseMetadata {
+    fn set(&mut self, key: &str, value: &str) {
+        // FTK Imager writes a single space for empty values
+        if value.trim().is_empty() {
+            return;
+        }
+
+        let v = Some(value.to_string());
+
+        match key {
+            "c" => self.case_number = v,
+            "n" => self.evidence_number = v,
+            "a" => self.description = v,
+            "e" => self.examiner_name = v,
+            "t" => self.notes = v,
+            "md" => self.model = v,
+            "sn" => self.serial_number = v,
+            "l" => self.device_label = v,
+            "av" => self.acquisition_software_version = v,
+            "ov" => self.acquisition_os = v,
+            "m" => self.acquisition_date = parse_date(value),
+            "u" => self.system_date = parse_date(value),
+            // a password hash of 0 means there is no password
+            "p" => if value != "0" { self.password_hash = v },
+            "r" => self.compression_level = v,
+            _ => self.other.push((key.into(), value.into()))
+        }
+    }
+
+    fn from_text(
+        section_type: &'static str,
+        text: &str
+    ) -> Result<Self, LibError>
+    {
+        let mut meta = CaseMetadata::default();
+
+        for (k, v) in main_category(section_type, text)? {
+            meta.set(&k, &v);
+        }
+
+        Ok(meta)
+    }
+
+    fn set_case_data(&mut self, key: &str, value: &str) {
+        if value.trim().is_empty() {
+            return;
+        }
+
+        let v = Some(value.to_string());
+
+        match key {
+            "nm" => self.description = v,
+            "cn" => self.case_number = v,
+            "en" => self.evidence_number = v,
+            "ex" => self.examiner_name = v,
+            "nt" => self.notes = v,
+            "av" => self.acquisition_software_version = v,
+            "os" => self.acquisition_os = v,
+            "at" => self.acquisition_date = parse_date(value),
+            "tt" => self.system_date = parse_date(value),
+            _ => self.other.push((key.into(), value.into()))
+        }
+    }
+
+    // EWF2 case data sections hold what header2 sections held in EWF1,
+    // but with different keys
+    pub(crate) fn from_case_data(pairs: &[(String, String)]) -> Self {
+        let mut meta = CaseMetadata::default();
+
+        for (k, v) in pairs {
+            meta.set_case_data(k, v);
+        }
+
+        meta
+    }
+
+    // EWF2 device information sections hold the device model, serial
+    // number, and label
+    pub(crate) fn set_device_information(&mut self, pairs: &[(String, String)]) {
+        for (k, v) in pairs {
+            if v.trim().is_empty() {
+                continue;
+            }
+
+            match k.as_str() {
+                "md" => self.model = Some(v.clone()),
+                "sn" => self.serial_number = Some(v.clone()),
+                "lb" => self.device_label = Some(v.clone()),
+                _ => {}
+            }
+        }
+    }
+
+    fn set_xheader(&mut self, key: &str, value: &str) {
+        if value.trim().is_empty() {
+            return;
+        }
+
+        let v = Some(value.to_string());
+
+        match key {
+            "case_number" => self.case_number = v,
+            "evidence_number" => self.evidence_number = v,
+            "description" => self.description = v,
+            "examiner_name" => self.examiner_name = v,
+            "notes" => self.notes = v,
+            "model" => self.model = v,
+            "serial_number" => self.serial_number = v,
+            "device_label" => self.device_label = v,
+            "acquiry_software_version" => self.acquisition_software_version = v,
+            "acquiry_operating_system" => self.acquisition_os = v,
+            "acquiry_date" => self.acquisition_date = Some(HeaderDate::Text(value.into())),
+            "system_date" => self.system_date = Some(HeaderDate::Text(value.into())),
+            "password" => self.password_hash = v,
+            "compression_level" => self.compression_level = v,
+            _ => self.other.push((key.into(), value.into()))
+        }
+    }
+
+    pub(crate) fn from_xheader(data: &[u8]) -> Result<Self, LibError> {
+        let text = String::from_utf8_lossy(data);
+
+        let elements = parse_flat_xml(&text, "xheader")
+            .map_err(|e| LibError::MalformedSection("xheader", e))?;
+
+        let mut meta = CaseMetadata::default();
+
+        for (k, v) in elements {
+            debug!("xheader {k} = {v}");
+            meta.set_xheader(&k, &v);
+        }
+
+        Ok(meta)
+    }
+
+    pub(crate) fn from_header(data: &[u8]) -> Result<Self, LibError> {
+        // header sections are in a single-byte codepage, which we treat as
+        // Latin-1 as that is identical to ASCII for the usual case
+        let text = data.iter()
+            .map(|&b| b as char)
+            .collect::<String>();
+
+        Self::from_text("header", &text)
+    }
+
+    pub(crate) fn from_header2(data: &[u8]) -> Result<Self, LibError> {
+        Self::from_text("header2", &decode_utf16le(data))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_header_ok() {
+        let data = b"1\r\nmain\r\nc\tn\ta\te\tt\tav\tov\tm\tu\tp\r\ncase 7\tev 1\tdisk\tJo\t \t6.1\tWindows\t2025 5 20 15 36 10\t2025 5 20 15 36 11\t0\r\n\r\n";
+
+        let exp = CaseMetadata {
+            case_number: Some("case 7".into()),
+            evidence_number: Some("ev 1".into()),
+            description: Some("disk".into()),
+            examiner_name: Some("Jo".into()),
+            acquisition_software_version: Some("6.1".into()),
+            acquisition_os: Some("Windows".into()),
+            acquisition_date: Some(HeaderDate::Local(2025, 5, 20, 15, 36, 10)),
+            system_date: Some(HeaderDate::Local(2025, 5, 20, 15, 36, 11)),
+            ..Default::default()
+        };
+
+        assert_eq!(CaseMetadata::from_header(data).unwrap(), exp);
+    }
+
+    #[test]
+    fn from_header2_ok() {
+        let text = "3\nmain\na\tc\tm\tu\tp\tdc\n\u{e9}t\u{e9}\t42\t1747751770\t1747751771\t\t\n\nsrce\n";
+        let data = [0xff, 0xfe].into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect::<Vec<_>>();
+
+        let exp = CaseMetadata {
+            description: Some("\u{e9}t\u{e9}".into()),
+            case_number: Some("42".into()),
+            acquisition_date: Some(HeaderDate::Unix(1747751770)),
+            system_date: Some(HeaderDate::Unix(1747751771)),
+            ..Default::default()
+        };
+
+        assert_eq!(CaseMetadata::from_header2(&data).unwrap(), exp);
+    }
+
+    #[test]
+    fn from_header_unknown_keys() {
+        let data = b"1\nmain\nc\tzz\n1\tfoo\n";
+
+        let exp = CaseMetadata {
+            case_number: Some("1".into()),
+            other: vec![("zz".into(), "foo".into())],
+            ..Default::default()
+        };
+
+        assert_eq!(CaseMetadata::from_header(data).unwrap(), exp);
+    }
+
+    #[test]
+    fn from_xheader_ok() {
+        let data = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<xheader>\n\t<case_number>7</case_number>\n\t<examiner_name>Jo</examiner_name>\n\t<acquiry_date>Sat Jan 20 18:32:08 2018 CET</acquiry_date>\n\t<unusual>x</unusual>\n</xheader>\n";
+
+        let exp = CaseMetadata {
+            case_number: Some("7".into()),
+            examiner_name: Some("Jo".into()),
+            acquisition_date: Some(HeaderDate::Text("Sat Jan 20 18:32:08 2018 CET".into())),
+            other: vec![("unusual".into(), "x".into())],
+            ..Default::default()
+        };
+
+        assert_eq!(CaseMetadata::from_xheader(data).unwrap(), exp);
+    }
+
+    #[test]
+    fn from_case_data_ok() {
+        let text = "1\nmain\nnm\tcn\tex\tat\tsb\n\u{e9}t\u{e9}\t42\tJo\t1747751770\t64\n\n";
+        let pairs = main_category("case data", text).unwrap();
+
+        let mut meta = CaseMetadata::from_case_data(&pairs);
+        meta.set_device_information(&[
+            ("sn".into(), "S123".into()),
+            ("ts".into(), "2048".into())
+        ]);
+
+        let exp = CaseMetadata {
+            description: Some("\u{e9}t\u{e9}".into()),
+            case_number: Some("42".into()),
+            examiner_name: Some("Jo".into()),
+            serial_number: Some("S123".into()),
+            acquisition_date: Some(HeaderDate::Unix(1747751770)),
+            other: vec![("sb".into(), "64".into())],
+            ..Default::default()
+        };
+
+        assert_eq!(meta, exp);
+    }
+
+    #[test]
+    fn from_header_no_main() {
+        assert!(matches!(
+            CaseMetadata::from_header(b"1\nsrce\n"),
+            Err(LibError::MalformedSection("header", _))
+        ));
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::e01_reader::ChunkCacheStats;
+
+// A least-recently-used cache of decompressed chunks, keyed by chunk
+// index, holding at most capacity bytes of chunk data
+pub struct ChunkCache {
+    capacity: usize,
+    size: usize,
+    // incremented on each use, to order the chunks by last use
+    tick: u64,
+    chunks: HashMap<usize, (u64, Vec<u8>)>,
+    // chunk indices by last use, oldest first
+    lru: BTreeMap<u64, usize>,
+    hits: u64,
+    misses: u64
+}
+
+impl ChunkCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            size: 0,
+            tick: 0,
+            chunks: HashMap::new(),
+            lru: BTreeMap::new(),
+            hits: 0,
+            misses: 0
+        }
+    }
+
+    // Copies the part of the chunk starting at beg into buf, if the chunk
+    // is cached.
+    pub fn read(
+        &mut self,
+        chunk_index: usize,
+        beg: usize,
+        buf: &mut [u8]
+    ) -> bool
+    {
+        match self.chunks.get_mut(&chunk_index) {
+            Some((tick, ch)) => {
+                self.lru.remove(tick);
+                self.tick += 1;
+                *tick = self.tick;
+                self.lru.insert(self.tick, chunk_index);
+
+                buf.copy_from_slice(&ch[beg..beg + buf.len()]);
+                self.hits += 1;
+                true
+            },
+            None => {
+                self.misses += 1;
+                false
+            }
+        }
+    }
+
+    // Caches the chunk, evicting the least recently used ones to make room.
+    // Returns a buffer no longer needed, either one evicted or the chunk if
+    // it is too large to keep, so that the caller can reuse it.
+    pub fn insert(&mut self, chunk_index: usize, ch: Vec<u8>) -> Option<Vec<u8>> {
+        if ch.len() > self.capacity {
+            return Some(ch);
+        }
+
+        let mut spare = None;
+
+        if let Some((tick, old)) = self.chunks.remove(&chunk_index) {
+            self.lru.remove(&tick);
+            self.size -= old.len();
+            spare = Some(old);
+        }
+
+        // evict the least recently used chunks to make room
+        while self.size + ch.len() > self.capacity {
+            let Some((_, victim)) = self.lru.pop_first() else {
+                break;
+            };
+
+            if let Some((_, old)) = self.chunks.remove(&victim) {
+                self.size -= old.len();
+                spare = Some(old);
+            }
+        }
+
+        self.tick += 1;
+        self.size += ch.len();
+        self.lru.insert(self.tick, chunk_index);
+        self.chunks.insert(chunk_index, (self.tick, ch));
+
+        spare
+    }
+
+    pub fn stats(&self) -> ChunkCacheStats {
+        ChunkCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            size: self.size
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_hit_miss() {
+        let mut cc = ChunkCache::new(100);
+        assert_eq!(cc.insert(3, (0..10).collect()), None);
+
+        let mut buf = [0; 4];
+        assert!(cc.read(3, 2, &mut buf));
+        assert_eq!(buf, [2, 3, 4, 5]);
+        assert!(!cc.read(4, 0, &mut buf));
+
+        assert_eq!(
+            cc.stats(),
+            ChunkCacheStats { hits: 1, misses: 1, size: 10 }
+        );
+    }
+
+    #[test]
+    fn insert_evicts_least_recently_used() {
+        let mut cc = ChunkCache::new(30);
+        cc.insert(0, vec![0; 10]);
+        cc.insert(1, vec![1; 10]);
+        cc.insert(2, vec![2; 10]);
+
+        // use 0, so that 1 is the least recently used
+        let mut buf = [0; 1];
+        assert!(cc.read(0, 0, &mut buf));
+
+        // the evicted chunk is given back
+        assert_eq!(cc.insert(3, vec![3; 10]), Some(vec![1; 10]));
+
+        assert!(cc.read(0, 0, &mut buf));
+        assert!(!cc.read(1, 0, &mut buf));
+        assert!(cc.read(2, 0, &mut buf));
+        assert!(cc.read(3, 0, &mut buf));
+        assert_eq!(cc.stats().size, 30);
+    }
+
+    #[test]
+    fn insert_replaces() {
+        let mut cc = ChunkCache::new(30);
+        cc.insert(0, vec![0; 10]);
+        assert_eq!(cc.insert(0, vec![1; 20]), Some(vec![0; 10]));
+
+        let mut buf = [0; 1];
+        assert!(cc.read(0, 19, &mut buf));
+        assert_eq!(buf, [1]);
+        assert_eq!(cc.stats().size, 20);
+    }
+
+    #[test]
+    fn insert_too_large() {
+        let mut cc = ChunkCache::new(5);
+        assert_eq!(cc.insert(0, vec![0; 10]), Some(vec![0; 10]));
+
+        let mut buf = [0; 1];
+        assert!(!cc.read(0, 0, &mut buf));
+        assert_eq!(cc.stats().size, 0);
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    sync::{
+        Arc,
+        mpsc::{self, Receiver}
+    }
+};
+
+use crate::e01_reader::{E01Reader, ReadError};
+
+// An iterator over the data of the chunks of a range of the image, in
+// order, one owned buffer per chunk; the first and last are trimmed to
+// the range. Up to depth chunks ahead of the consumer are read and
+// decompressed in the background, on the rayon thread pool.
+pub struct ChunkStream {
+    reader: Arc<E01Reader>,
+    // the offset of the next chunk to request, and the end of the range
+    next: u64,
+    end: u64,
+    depth: usize,
+    // the results of the requested chunks, in order
+    pending: VecDeque<Receiver<Result<Vec<u8>, ReadError>>>
+}
+
+impl ChunkStream {
+    pub fn new(
+        reader: Arc<E01Reader>,
+        range: Range<u64>,
+        depth: usize
+    ) -> Self
+    {
+        let end = range.end.min(reader.image_size);
+
+        Self {
+            reader,
+            next: range.start.min(end),
+            end,
+            depth: depth.max(1),
+            pending: VecDeque::new()
+        }
+    }
+
+    fn request_next(&mut self) {
+        let chunk_size = self.reader.chunk_size as u64;
+        let beg = self.next;
+        let end = ((beg / chunk_size + 1) * chunk_size).min(self.end);
+        self.next = end;
+
+        let (tx, rx) = mpsc::sync_channel(1);
+        let reader = self.reader.clone();
+
+        rayon::spawn(move || {
+            let mut buf = vec![0; (end - beg) as usize];
+            let r = reader.read_at(beg, &mut buf).map(|_| buf);
+            // the stream may have been dropped; nobody wants the chunk then
+            let _ = tx.send(r);
+        });
+
+        self.pending.push_back(rx);
+    }
+}
+
+impl Iterator for ChunkStream {
+    type Item = Result<Vec<u8>, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // keep depth chunks in flight
+        while self.pending.len() < self.depth && self.next < self.end {
+            self.request_next();
+        }
+
+        let r = self.pending.pop_front()?
+            .recv()
+            .expect("chunk task cannot have dropped its sender");
+
+        if r.is_err() {
+            // stop after an error
+            self.next = self.end;
+            self.pending.clear();
+        }
+
+        Some(r)
+    }
+}
@@ -0,0 +1,135 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::e01_reader::E01Reader;
+
+// The number of chunks to read at once when filling the buffer; reading
+// many chunks per call lets read_at decompress them in parallel.
+pub(crate) const BUFFER_CHUNKS: usize = 64;
+
+// A Read + Seek view of the whole image. Small reads are served from a
+// buffer spanning many chunks, so that sequential reads through e.g.
+// std::io::copy are as fast as large read_at calls.
+pub struct E01Cursor<'a> {
+    reader: &'a E01Reader,
+    pos: u64,
+    buf: Vec<u8>,
+    buf_beg: u64,
+    buf_len: usize
+}
+
+impl<'a> E01Cursor<'a> {
+    pub fn new(reader: &'a E01Reader) -> Self {
+        let capacity = reader.chunk_size * BUFFER_CHUNKS;
+        Self::with_capacity(reader, capacity)
+    }
+
+    pub fn with_capacity(reader: &'a E01Reader, capacity: usize) -> Self {
+        Self {
+            reader,
+            pos: 0,
+            buf: vec![0; capacity],
+            buf_beg: 0,
+            buf_len: 0
+        }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn len(&self) -> u64 {
+        self.reader.image_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reader.image_size == 0
+    }
+
+    fn buffered(&self) -> Option<&[u8]> {
+        let beg = self.pos.checked_sub(self.buf_beg)?;
+        if beg < self.buf_len as u64 {
+            Some(&self.buf[beg as usize..self.buf_len])
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl Read for E01Cursor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.reader.image_size || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.buffered().is_none() {
+            // reads at least as large as the buffer bypass it
+            if buf.len() >= self.buf.len() {
+                let read = self.reader.read_at(self.pos, buf)
+                    .map_err(std::io::Error::other)?;
+                self.pos += read as u64;
+                return Ok(read);
+            }
+
+            self.buf_len = self.reader.read_at(self.pos, &mut self.buf)
+                .map_err(std::io::Error::other)?;
+            self.buf_beg = self.pos;
+        }
+
+        let src = self.buffered().unwrap_or_default();
+        let len = src.len().min(buf.len());
+        buf[..len].copy_from_slice(&src[..len]);
+
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl Seek for E01Cursor<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.pos = seek_position(pos, self.pos, self.reader.image_size)?;
+        Ok(self.pos)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+// Returns the position a seek moves to from pos, for data of length len.
+pub(crate) fn seek_position(
+    seek: SeekFrom,
+    pos: u64,
+    len: u64
+) -> std::io::Result<u64>
+{
+    match seek {
+        SeekFrom::Start(off) => Some(off),
+        SeekFrom::End(off) => len.checked_add_signed(off),
+        SeekFrom::Current(off) => pos.checked_add_signed(off)
+    }
+    .ok_or_else(|| std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "invalid seek to a negative or overflowing position"
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seek_position_ok() {
+        assert_eq!(seek_position(SeekFrom::Start(7), 3, 10).unwrap(), 7);
+        assert_eq!(seek_position(SeekFrom::End(-4), 3, 10).unwrap(), 6);
+        assert_eq!(seek_position(SeekFrom::End(5), 3, 10).unwrap(), 15);
+        assert_eq!(seek_position(SeekFrom::Current(-3), 3, 10).unwrap(), 0);
+    }
+
+    #[test]
+    fn seek_position_bad() {
+        assert!(seek_position(SeekFrom::End(-11), 3, 10).is_err());
+        assert!(seek_position(SeekFrom::Current(-4), 3, 10).is_err());
+        assert!(seek_position(SeekFrom::Current(1), u64::MAX, 10).is_err());
+    }
+}
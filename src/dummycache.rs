@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use std::sync::Mutex;
 
 use crate::{
     bytessource::BytesSource,
@@ -7,39 +8,54 @@ use crate::{
 };
 
 pub struct DummyCache {
-    sources: Vec<Box<dyn BytesSource + Send>>
+    sources: Mutex<Vec<Box<dyn BytesSource + Send>>>
 }
 
 impl DummyCache {
     pub fn new() -> Self {
-        Self { sources: vec![] }
+        Self { sources: Mutex::new(vec![]) }
     }
 }
 
 #[async_trait]
 impl Cache for DummyCache {
     async fn read(
-        &mut self,
+        &self,
         idx: usize,
         off: u64,
         buf: &mut [u8]
     ) -> Result<(), std::io::Error>
     {
-        let b = self.sources[idx].read(off, off + buf.len() as u64).await?;
+        let read = self.sources.lock()
+            .expect("poisoned")
+            .get(idx)
+            .ok_or(std::io::Error::other(format!("{idx} out of bounds")))?
+            .read(off, off + buf.len() as u64);
+
+        let b = read.await?;
         buf.copy_from_slice(&b);
         Ok(())
     }
 
     fn end(&self, idx: usize) -> Result<u64, std::io::Error> {
-        self.sources.get(idx)
+        self.sources.lock()
+            .expect("poisoned")
+            .get(idx)
             .ok_or(std::io::Error::other(format!("{idx} out of bounds")))
             .map(|src| src.end())
     }
 
-    fn add_source(&mut self, idx: usize, src: Box<dyn BytesSource + Send>) {
-        if self.sources.len() <= idx {
-            self.sources.resize_with(idx + 1, || Box::new(PlaceholderSource));
+    fn add_source(
+        &self,
+        idx: usize,
+        _name: &str,
+        src: Box<dyn BytesSource + Send>
+    )
+    {
+        let mut sources = self.sources.lock().expect("poisoned");
+        if sources.len() <= idx {
+            sources.resize_with(idx + 1, || Box::new(PlaceholderSource));
         }
-        self.sources[idx] = src;
+        sources[idx] = src;
     }
 }
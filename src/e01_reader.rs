@@ -7,27 +7,44 @@ use s3::{
 };
 use std::{
     fmt::Debug,
+    io::Write,
+    ops::Range,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex}
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration
 };
-use tokio::runtime::Runtime;
+use tokio::runtime::{Handle, Runtime, RuntimeFlavor};
 use tracing::{debug, debug_span, trace, warn};
 use url::{self, Url};
 
 use crate::{
+    blocking::block_on,
+    bufworkersource::BufWorkerSource,
     bytessource::BytesSource,
     cache::Cache,
     cachereadseek::CacheReadSeek,
     cacheworkersource::CacheWorkerSource,
+    case_metadata::CaseMetadata,
+    chunkcache::ChunkCache,
+    chunkstream::ChunkStream,
+    cursor::E01Cursor,
     dummycache::DummyCache,
     error::{IoError, LibError},
     foyercache::FoyerCache,
-    filesource::FileSource,
+    filepool::FilePool,
+    filesource::{FileSource, file_version},
+    httpsource::{HttpSource, head_length_version, request_error},
+    mapfile::write_ddrescue_mapfile,
+    media_info::MediaInfo,
     readworker::ReadWorker,
-    s3source::S3Source,
+    retrysource::RetrySource,
+    s3source::{S3Source, s3_error},
     sec_read::{Chunk, VolumeSection, Section, SectionIterator},
-    seg_path::{ExistsChecker, UnrecognizedExtension, validated_segment_paths},
-    segment::SegmentFileHeader
+    sec_read_v2::{SectionIteratorV2, volume_section},
+    seg_path::{ExistsChecker, SegmentPathError, UnrecognizedExtension, listed_segment_paths, validated_segment_paths},
+    segment::{CompressionMethod, SegmentFileHeader},
+    session::{SectorRangeReader, Session},
+    workersource::WorkerSource
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -35,7 +52,11 @@ pub enum InitError {
     #[error("Failed to start tokio Runtime: {0}")]
     TokioRuntimeFailed(std::io::Error),
     #[error("{0}")]
-    CacheSetupFailed(std::io::Error)
+    CacheSetupFailed(std::io::Error),
+    #[error("Open task failed: {0}")]
+    OpenTaskFailed(tokio::task::JoinError),
+    #[error("A multi-threaded tokio runtime is required")]
+    CurrentThreadRuntime
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -46,6 +67,10 @@ pub enum OpenError {
     NoSegmentFiles,
     #[error("Missing volume section in {0}")]
     MissingVolumeSection(PathBuf),
+    #[error("Missing ltree section; not a logical evidence file?")]
+    MissingLTreeSection,
+    #[error("Bad ltree section: {0}")]
+    BadLTreeSection(String),
     #[error("Too many chunks found: actual {0}, expected {1}")]
     TooManyChunks(usize, usize),
     #[error("Too few chunks found: actual {0}, expected {1}")]
@@ -91,6 +116,16 @@ impl From<LibError> for OpenError {
     }
 }
 
+impl From<SegmentPathError> for OpenError {
+    fn from(e: SegmentPathError) -> Self {
+        match e {
+            SegmentPathError::UnrecognizedExtension(e) => e.into(),
+            SegmentPathError::CheckFailed(path, e) =>
+                OpenError::from(e).with_path(path)
+        }
+    }
+}
+
 impl From<KError> for OpenError {
     fn from(e: KError) -> Self {
         Self::IoError {
@@ -170,18 +205,91 @@ pub enum E01Error {
 
 #[derive(Debug)]
 struct Segment {
-    pub path: String
+    pub path: String,
+    pub compr_method: CompressionMethod
 }
 
 struct SegmentComponents {
     path: String,
+    compr_method: CompressionMethod,
+    case_metadata: Option<CaseMetadata>,
     volume: Option<VolumeSection>,
     md5: Option<[u8; 16]>,
     sha1: Option<[u8; 20]>,
+    xhash: Vec<(String, String)>,
+    acquisition_errors: Vec<(u64, u64)>,
+    sessions: Vec<(u64, u32)>,
+    ltree: Option<String>,
     chunks: Vec<Chunk>,
+    table_copies: Vec<TableCopy>,
     done: bool
 }
 
+// which copy of a chunk table was used
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableCopy {
+    Table,
+    Table2
+}
+
+// Chooses between the primary and backup copies of a chunk table,
+// preferring the primary when both are readable.
+fn choose_table(
+    segment_path: &str,
+    table: Option<Result<Vec<Chunk>, LibError>>,
+    table2: Option<Result<Vec<Chunk>, LibError>>
+) -> Result<(Vec<Chunk>, TableCopy), LibError>
+{
+    match (table, table2) {
+        (Some(Ok(t)), t2) => {
+            match t2 {
+                Some(Ok(t2)) if t != t2 =>
+                    warn!("{segment_path}: table and table2 disagree; using table"),
+                Some(Err(e)) =>
+                    warn!("{segment_path}: table2 is unreadable: {e}"),
+                _ => {}
+            }
+            Ok((t, TableCopy::Table))
+        },
+        (Some(Err(e)), Some(Ok(t2))) => {
+            warn!("{segment_path}: table is unreadable, using table2: {e}");
+            Ok((t2, TableCopy::Table2))
+        },
+        (None, Some(Ok(t2))) => {
+            warn!("{segment_path}: table2 without table, using table2");
+            Ok((t2, TableCopy::Table2))
+        },
+        (Some(Err(e)), _) |
+        (None, Some(Err(e))) => Err(e),
+        (None, None) => Ok((vec![], TableCopy::Table))
+    }
+}
+
+fn add_table<T: AsRef<str>>(
+    segment_path: T,
+    table: Option<Result<Vec<Chunk>, LibError>>,
+    table2: Option<Result<Vec<Chunk>, LibError>>,
+    end_of_sectors: u64,
+    chunks: &mut Vec<Chunk>,
+    table_copies: &mut Vec<TableCopy>
+) -> Result<(), OpenError>
+{
+    let (t, copy) = choose_table(segment_path.as_ref(), table, table2)
+        .map_err(OpenError::from)
+        .map_err(|e| e.with_path(&segment_path))?;
+
+    if !t.is_empty() {
+        chunks.extend(t);
+        // set the end of the last chunk in the table
+        let chunks_len = chunks.len();
+        chunks[chunks_len - 1].end_offset = end_of_sectors;
+    }
+
+    table_copies.push(copy);
+
+    Ok(())
+}
+
 fn read_segment<T: AsRef<str>>(
     segment_path: T,
     segment_index: usize,
@@ -191,7 +299,7 @@ fn read_segment<T: AsRef<str>>(
 {
     debug!("reading sections {}", segment_path.as_ref());
 
-    let _header = SegmentFileHeader::new(io)
+    let file_header = SegmentFileHeader::new(io)
         .map_err(OpenError::from)
         .map_err(|e| e.with_path(&segment_path))?;
 
@@ -203,11 +311,34 @@ fn read_segment<T: AsRef<str>>(
 
     let mut end_of_sectors = 0;
 
+    // a table waiting for its table2
+    let mut pending_table = None;
+    let mut table_copies = vec![];
+
+    let mut header = None;
+    let mut header2 = None;
+    let mut xheader = None;
+    let mut device_information = None;
+    let mut case_data = None;
     let mut volume = None;
     let mut md5 = None;
     let mut sha1 = None;
-
-    let mut sections = SectionIterator::new(io, ignore_checksums);
+    let mut xhash = vec![];
+    let mut acquisition_errors = vec![];
+    let mut sessions = vec![];
+    let mut ltree = None;
+
+    let mut sections: Box<dyn Iterator<Item = Result<Section, LibError>> + '_> =
+        if file_header.major_version >= 2 {
+            Box::new(
+                SectionIteratorV2::new(io, ignore_checksums)
+                    .map_err(OpenError::from)
+                    .map_err(|e| e.with_path(&segment_path))?
+            )
+        }
+        else {
+            Box::new(SectionIterator::new(io, ignore_checksums))
+        };
 
     for section in sections.by_ref() {
         let section = section
@@ -216,27 +347,65 @@ fn read_segment<T: AsRef<str>>(
 
         debug!("found section {section:?}");
 
+        // a table without a table2 following it stands alone
+        if !matches!(section, Section::Table2(_)) &&
+            let Some(t) = pending_table.take()
+        {
+            add_table(
+                &segment_path,
+                Some(t),
+                None,
+                end_of_sectors,
+                &mut chunks,
+                &mut table_copies
+            )?;
+        }
+
         match section {
+            // header sections may be repeated; keep the first of each
+            Section::Header(h) => { header.get_or_insert(h); },
+            Section::Header2(h) => { header2.get_or_insert(h); },
+            Section::XHeader(h) => { xheader.get_or_insert(h); },
             Section::Volume(v) => volume = Some(v),
-            Section::Table(t) => {
-                if !t.is_empty() {
-                    chunks.extend(t);
-                    // set the end of the last chunk in the table
-                    let chunks_len = chunks.len();
-                    chunks[chunks_len - 1].end_offset = end_of_sectors;
-                }
-            },
+            Section::Table(t) => pending_table = Some(t),
+            Section::Table2(t2) => add_table(
+                &segment_path,
+                pending_table.take(),
+                Some(t2),
+                end_of_sectors,
+                &mut chunks,
+                &mut table_copies
+            )?,
+            Section::SectorTable(t) => chunks.extend(t),
+            Section::DeviceInformation(d) => device_information = Some(d),
+            Section::CaseData(c) => case_data = Some(c),
             Section::Sectors(eos) => end_of_sectors = eos,
             Section::Hash(h) => md5 = Some(h),
             Section::Digest(d_md5, d_sha1) => {
                 md5 = Some(d_md5);
                 sha1 = Some(d_sha1);
             },
+            Section::Sha1(h) => sha1 = Some(h),
+            Section::XHash(h) => xhash = h,
+            Section::Error(e) => acquisition_errors = e,
+            Section::Session(s) => sessions = s,
+            Section::LTree(t) => ltree = Some(t),
             Section::Done => { done = true; break; },
             _ => {}
         }
     }
 
+    if let Some(t) = pending_table.take() {
+        add_table(
+            &segment_path,
+            Some(t),
+            None,
+            end_of_sectors,
+            &mut chunks,
+            &mut table_copies
+        )?;
+    }
+
     if done && sections.next().is_some() {
         warn!("more sections after done");
     }
@@ -246,34 +415,78 @@ fn read_segment<T: AsRef<str>>(
         c.segment = segment_index;
     }
 
+    let case_metadata = match case_data {
+        // EWF2
+        Some(c) => {
+            if let Some(d) = &device_information && volume.is_none() {
+                volume = Some(
+                    volume_section(d, &c)
+                        .map_err(OpenError::from)
+                        .map_err(|e| e.with_path(&segment_path))?
+                );
+            }
+
+            let mut meta = c.metadata;
+            if let Some(d) = &device_information {
+                meta.set_device_information(&d.values);
+            }
+            Some(meta)
+        },
+        // EWF1; prefer the newest header format present
+        None => xheader.or(header2).or(header)
+    };
+
     Ok(
         SegmentComponents {
             path: segment_path.as_ref().into(),
+            compr_method: file_header.compr_method,
+            case_metadata,
             volume,
             md5,
             sha1,
+            xhash,
+            acquisition_errors,
+            sessions,
+            ltree,
             chunks,
+            table_copies,
             done
         }
     )
 }
 
-fn make_bytes_reader(
+fn source_for_path(
     p: &str,
-    idx: usize,
-    cache: Arc<Mutex<dyn Cache + Send>>,
-    runtime: Arc<Runtime>
-) -> Result<BytesReader, OpenError>
+    options: &E01ReaderOptions,
+    files: &Arc<FilePool>,
+    http: &OnceLock<reqwest::Client>,
+    runtime: &Handle
+) -> Result<Box<dyn BytesSource + Send>, OpenError>
 {
     debug!("opening {}", p);
 
     let url = path_or_url_to_url(p)
         .ok_or(OpenError::BadPath(p.into()))?;
 
-    let src = source_for_url(&url, &runtime)?;
+    source_for_url(&url, options, files, http, runtime)
+}
 
+fn make_bytes_reader(
+    p: &str,
+    src: Box<dyn BytesSource + Send>,
+    idx: usize,
+    cache: Arc<dyn Cache + Send + Sync>,
+    runtime: Handle
+) -> Result<BytesReader, OpenError>
+{
     let seg_len = src.end();
-    cache.lock().unwrap().add_source(idx, src);
+    // name the source by its URL, so that relative paths from different
+    // directories aren't confused in a persistent cache
+    let name = path_or_url_to_url(p)
+        .map(String::from)
+        .unwrap_or_else(|| p.into());
+
+    cache.add_source(idx, &name, src);
 
     let crs = CacheReadSeek::new(
         cache,
@@ -290,12 +503,26 @@ fn make_bytes_reader(
 }
 
 struct E01Metadata {
+    case_metadata: Option<CaseMetadata>,
     volume: VolumeSection,
     md5: Option<[u8; 16]>,
     sha1: Option<[u8; 20]>,
+    hashes: Vec<(String, String)>,
+    acquisition_errors: Vec<(u64, u64)>,
+    sessions: Vec<(u64, u32)>,
+    ltree: Option<String>,
     segments: Vec<Segment>,
     segment_paths: Vec<PathBuf>,
-    chunks: Vec<Chunk>
+    chunks: Vec<Chunk>,
+    table_copies: Vec<TableCopy>
+}
+
+fn hex_to_array<const N: usize>(s: &str) -> Option<[u8; N]> {
+    let mut a = [0; N];
+    hex::decode_to_slice(s, &mut a)
+        .inspect_err(|_| warn!("malformed stored hash {s}"))
+        .ok()
+        .map(|_| a)
 }
 
 fn process_segments<S: IntoIterator<Item = SegmentComponents>>(
@@ -303,13 +530,19 @@ fn process_segments<S: IntoIterator<Item = SegmentComponents>>(
     ignore_checksums: bool
 ) -> Result<E01Metadata, OpenError>
 {
+    let mut case_metadata = None;
     let mut volume = None;
     let mut stored_md5 = None;
     let mut stored_sha1 = None;
+    let mut stored_hashes = vec![];
+    let mut acquisition_errors = vec![];
+    let mut sessions = vec![];
+    let mut ltree = None;
 
     let mut segments = vec![];
     let mut segment_paths = vec![];
     let mut chunks = vec![];
+    let mut table_copies = vec![];
 
     let mut done = false;
 
@@ -337,6 +570,11 @@ fn process_segments<S: IntoIterator<Item = SegmentComponents>>(
                 warn!("duplicate volume section")
         }
 
+        // take the case metadata if it's the first one
+        if case_metadata.is_none() {
+            case_metadata = seg.case_metadata;
+        }
+
         // take the stored MD5 if it's the first one
         match (seg.md5, &stored_md5) {
             (Some(h), None) => stored_md5 = Some(h),
@@ -353,12 +591,38 @@ fn process_segments<S: IntoIterator<Item = SegmentComponents>>(
             _ => {}
         }
 
+        // take the xhash hashes if they're the first ones
+        if stored_hashes.is_empty() {
+            stored_hashes = seg.xhash;
+        }
+
+        // take the acquisition errors if they're the first ones
+        if acquisition_errors.is_empty() {
+            acquisition_errors = seg.acquisition_errors;
+        }
+
+        // take the sessions if they're the first ones
+        if sessions.is_empty() {
+            sessions = seg.sessions;
+        }
+
+        // take the ltree if it's the first one
+        if ltree.is_none() {
+            ltree = seg.ltree;
+        }
+
         // record the chunks
         chunks.extend(seg.chunks);
+        table_copies.extend(seg.table_copies);
 
         // record the segment
         segment_paths.push((&seg.path).into());
-        segments.push(Segment { path: seg.path });
+        segments.push(
+            Segment {
+                path: seg.path,
+                compr_method: seg.compr_method
+            }
+        );
 
         if seg.done {
             if done {
@@ -376,18 +640,67 @@ fn process_segments<S: IntoIterator<Item = SegmentComponents>>(
 
     let volume = volume.expect("volume section must have been found");
 
+    // use the xhash MD5 and SHA1 if there were no hash or digest sections
+    for (name, value) in &stored_hashes {
+        match name.as_str() {
+            "md5" => match (hex_to_array(value), &stored_md5) {
+                (Some(h), None) => stored_md5 = Some(h),
+                (Some(new), Some(old)) if new != *old =>
+                    warn!("xhash MD5 disagrees with stored MD5"),
+                _ => {}
+            },
+            "sha1" => match (hex_to_array(value), &stored_sha1) {
+                (Some(h), None) => stored_sha1 = Some(h),
+                (Some(new), Some(old)) if new != *old =>
+                    warn!("xhash SHA1 disagrees with stored SHA1"),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
     Ok(
         E01Metadata {
+            case_metadata,
             volume,
             md5: stored_md5,
             sha1: stored_sha1,
+            hashes: stored_hashes,
+            acquisition_errors,
+            sessions,
+            ltree,
             segments,
             segment_paths,
-            chunks
+            chunks,
+            table_copies
         }
     )
 }
 
+// Chunks whose table entries overflowed have lost their compression flags.
+// Uncompressed chunks are stored as the chunk data followed by a 4-byte
+// checksum, so any chunk shorter than that must be compressed.
+fn resolve_overflowed_chunks(
+    chunks: &mut [Chunk],
+    chunk_size: usize,
+    image_size: u64
+)
+{
+    let chunk_size = chunk_size as u64;
+
+    for (i, c) in chunks.iter_mut()
+        .enumerate()
+        .filter(|(_, c)| c.compression_unknown)
+    {
+        let data_len = chunk_size.min(image_size.saturating_sub(i as u64 * chunk_size));
+        let stored_len = c.end_offset.saturating_sub(c.data_offset);
+
+        c.compressed = stored_len < data_len + 4;
+        c.has_checksum = !c.compressed;
+        c.compression_unknown = false;
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum CorruptSectionPolicy {
     #[default]
@@ -404,9 +717,165 @@ pub enum CorruptChunkPolicy {
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum S3Credentials {
+    #[default]
+    Anonymous,
+    Explicit {
+        access_key: String,
+        secret_key: String,
+        session_token: Option<String>
+    },
+    // from AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, etc.
+    Environment,
+    // from a section of ~/.aws/credentials, or the default section if none
+    Profile(Option<String>)
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct S3Options {
+    // e.g., "eu-west-1"; us-east-1 if none is given
+    pub region: Option<String>,
+    // for S3-compatible stores, e.g., "http://minio.example.com:9000"
+    pub endpoint: Option<String>,
+    pub path_style: bool,
+    pub credentials: S3Credentials
+}
+
+impl S3Options {
+    fn bucket(&self, name: &str) -> Result<Bucket, std::io::Error> {
+        let region = match &self.endpoint {
+            Some(endpoint) => Region::Custom {
+                region: self.region.clone()
+                    .unwrap_or_else(|| "us-east-1".into()),
+                endpoint: endpoint.clone()
+            },
+            None => match &self.region {
+                Some(region) => region.parse()
+                    .map_err(std::io::Error::other)?,
+                None => Region::UsEast1
+            }
+        };
+
+        let credentials = match &self.credentials {
+            S3Credentials::Anonymous => Credentials::anonymous(),
+            S3Credentials::Explicit { access_key, secret_key, session_token } =>
+                Credentials::new(
+                    Some(access_key),
+                    Some(secret_key),
+                    None,
+                    session_token.as_deref(),
+                    None
+                ),
+            S3Credentials::Environment => Credentials::from_env(),
+            S3Credentials::Profile(section) =>
+                Credentials::from_profile(section.as_deref())
+        }
+        .map_err(std::io::Error::other)?;
+
+        let bucket = Bucket::new(name, region, credentials)
+            .map_err(std::io::Error::other)?;
+
+        Ok(if self.path_style {
+            *bucket.with_path_style()
+        }
+        else {
+            *bucket
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryOptions {
+    // the number of times to retry a failed read; 0 to never retry
+    pub max_retries: u32,
+    // the delay before a retry is random, up to initial_backoff doubled
+    // for each earlier retry, but no more than max_backoff
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // the limit for each attempt at a read
+    pub request_timeout: Option<Duration>,
+    // the limit for all attempts at a read, including the delays
+    pub deadline: Option<Duration>
+}
+
+impl Default for RetryOptions {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            request_timeout: Some(Duration::from_secs(60)),
+            deadline: Some(Duration::from_secs(300))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheOptions {
+    // segments are read and cached in blocks of this size
+    pub block_size: usize,
+    // the memory and disk budgets, in bytes; no disk cache if 0
+    pub mem_size: usize,
+    pub disk_size: usize,
+    // the number of blocks to read ahead of each read, in the background
+    pub readahead: usize,
+    // the budget, in bytes, for decompressed chunks; none are kept if 0,
+    // the default. Parts of chunks read while it is enabled are read by
+    // decompressing and caching the whole chunk, which speeds up small
+    // repeated reads, e.g., of file system metadata.
+    pub chunk_cache_size: usize,
+    // a directory for the disk cache, which is reused by later readers with
+    // the same directory; a temporary one if none is given. A disk_size
+    // must be given with it. Blocks are keyed by segment URL, length, and
+    // version, e.g., modification time or ETag, and those of segments with
+    // no version aren't reused. Sources given to open_sources are named by
+    // index, so their versions must tell them apart to share a directory.
+    pub dir: Option<PathBuf>
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            block_size: 1024 * 1024,
+            mem_size: 256 * 1024 * 1024,
+            disk_size: 0,
+            readahead: 0,
+            chunk_cache_size: 0,
+            dir: None
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    // bytes of decompressed chunks held
+    pub size: usize
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct E01ReaderOptions {
     pub corrupt_section_policy: CorruptSectionPolicy,
-    pub corrupt_chunk_policy: CorruptChunkPolicy
+    pub corrupt_chunk_policy: CorruptChunkPolicy,
+    // A multi-threaded runtime to use, which may be shared among readers;
+    // if none is given, open and open_glob create one for each reader and
+    // the async opens use the current one. The blocking API may be called
+    // from async code, but blocks the calling thread.
+    pub runtime: Option<Handle>,
+    // settings for s3:// segments; anonymous access to us-east-1 if none
+    pub s3: Option<S3Options>,
+    // for reads of s3:// and http(s):// segments; the defaults if none
+    pub retry: Option<RetryOptions>,
+    // the block cache between segment storage and the reader; the
+    // defaults if none
+    pub cache: Option<CacheOptions>,
+    // the most chunks which one read decompresses at once, which bounds
+    // its memory use; the number of rayon threads if none
+    pub read_parallelism: Option<usize>,
+    // the most segment files to hold open at once; 256 or half the
+    // process's limit on open files, whichever is less, if none
+    pub max_open_files: Option<usize>
 }
 
 fn path_or_url_to_url<P: AsRef<str>>(p: P) -> Option<Url> {
@@ -424,9 +893,14 @@ fn path_or_url_to_url<P: AsRef<str>>(p: P) -> Option<Url> {
     }
 }
 
+// The HTTP client is made on first use, and shared by the segments so
+// that they share its connections.
 fn source_for_url(
     url: &Url,
-    runtime: &Runtime
+    options: &E01ReaderOptions,
+    files: &Arc<FilePool>,
+    http: &OnceLock<reqwest::Client>,
+    runtime: &Handle
 ) -> Result<Box<dyn BytesSource + Send>, OpenError>
 {
     match url.scheme() {
@@ -440,36 +914,59 @@ fn source_for_url(
                 url.path()
             };
 
-            let len = std::fs::metadata(p)
+            let meta = std::fs::metadata(p)
                 .map_err(OpenError::from)
-                .map_err(|e| e.with_path(p))?
-                .len();
-            Ok(Box::new(FileSource { path: p.into(), len }))
+                .map_err(|e| e.with_path(p))?;
+            Ok(Box::new(FileSource {
+                path: p.into(),
+                len: meta.len(),
+                version: file_version(&meta),
+                files: files.clone()
+            }))
         },
         "s3" => {
             let name = url.host_str()
                 .ok_or(OpenError::BadPath(url.to_string()))?;
 
-            let bucket = *Bucket::new(
-                name,
-                Region::UsEast1,
-                Credentials::anonymous().unwrap()
-            )
-            .map_err(std::io::Error::other)
-            .map_err(OpenError::from)
-            .map_err(|e| e.with_path(url))?;
+            let bucket = options.s3.clone()
+                .unwrap_or_default()
+                .bucket(name)
+                .map_err(OpenError::from)
+                .map_err(|e| e.with_path(url))?;
 
             let key = url.path();
 
-            let (h, _) = runtime.block_on(bucket.head_object(key))
-                .map_err(std::io::Error::other)
+            let (h, _) = block_on(runtime, bucket.head_object(key))
+                .map_err(s3_error)
                 .map_err(OpenError::from)
                 .map_err(|e| e.with_path(url))?;
 
-            let len = h.content_length.unwrap().try_into().unwrap();
+            let len = h.content_length
+                .and_then(|len| u64::try_from(len).ok())
+                .ok_or(std::io::Error::other("missing or bad Content-Length"))
+                .map_err(OpenError::from)
+                .map_err(|e| e.with_path(url))?;
             debug!("content-length: {len}");
 
-            Ok(Box::new(S3Source::new(bucket, key.into(), len)))
+            let version = h.e_tag.or(h.last_modified);
+
+            Ok(Box::new(RetrySource::new(
+                Arc::new(S3Source::new(bucket, key.into(), len, version)),
+                options.retry.clone().unwrap_or_default()
+            )))
+        },
+        "http" | "https" => {
+            let client = http.get_or_init(reqwest::Client::new).clone();
+
+            let (len, version) = block_on(runtime, head_length_version(&client, url.clone()))
+                .map_err(OpenError::from)
+                .map_err(|e| e.with_path(url))?;
+            debug!("content-length: {len}");
+
+            Ok(Box::new(RetrySource::new(
+                Arc::new(HttpSource::new(client, url.clone(), len, version)),
+                options.retry.clone().unwrap_or_default()
+            )))
         },
         _ => Err(OpenError::UnsupportedScheme(url.to_string()))
     }
@@ -487,15 +984,33 @@ pub struct E01Reader {
 
     pub stored_md5: Option<[u8; 16]>,
     pub stored_sha1: Option<[u8; 20]>,
+    // all hashes from the xhash section of the first segment which has one,
+    // as (lowercase name, hex) pairs
+    pub stored_hashes: Vec<(String, String)>,
+
+    pub case_metadata: Option<CaseMetadata>,
+    pub media_info: Option<MediaInfo>,
+
+    acquisition_errors: Vec<(u64, u64)>,
+    sessions: Vec<Session>,
+    table_copies: Vec<TableCopy>,
+    ltree: Option<String>,
 
     pub segment_paths: Vec<PathBuf>,
 
     corrupt_section_policy: CorruptSectionPolicy,
     corrupt_chunk_policy: CorruptChunkPolicy,
 
-    workers: Vec<ReadWorker>,
-    cache: Arc<Mutex<dyn Cache + Send>>,
-    runtime: Arc<Runtime>
+    // idle workers, taken by reads and returned when they finish
+    workers: Mutex<Vec<ReadWorker>>,
+    // the most workers used by one read
+    max_workers: usize,
+    // recently used decompressed chunks, if enabled
+    chunk_cache: Option<Mutex<ChunkCache>>,
+    cache: Arc<dyn Cache + Send + Sync>,
+    runtime: Handle,
+    // the runtime, if we created it rather than using the caller's
+    _owned_runtime: Option<Runtime>
 }
 
 impl Debug for E01Reader {
@@ -510,6 +1025,12 @@ impl Debug for E01Reader {
             .field("image_size", &self.image_size)
             .field("stored_md5", &self.stored_md5)
             .field("stored_sha1", &self.stored_sha1)
+            .field("stored_hashes", &self.stored_hashes)
+            .field("case_metadata", &self.case_metadata)
+            .field("media_info", &self.media_info)
+            .field("acquisition_errors", &self.acquisition_errors)
+            .field("sessions", &self.sessions)
+            .field("table_copies", &self.table_copies)
             .field("segment_paths", &self.segment_paths)
             .field("corrupt_section_policy", &self.corrupt_section_policy)
             .field("corrupt_chunk_policy", &self.corrupt_chunk_policy)
@@ -517,48 +1038,144 @@ impl Debug for E01Reader {
     }
 }
 
+// Returns a handle to the runtime to use, and the runtime itself if we had
+// to create it
+fn runtime_for(
+    options: &E01ReaderOptions
+) -> Result<(Handle, Option<Runtime>), InitError>
+{
+    match &options.runtime {
+        Some(handle) => Ok((multi_thread(handle.clone())?, None)),
+        None => {
+            let runtime = tokio::runtime::Runtime::new()
+                .map_err(InitError::TokioRuntimeFailed)?;
+            Ok((runtime.handle().clone(), Some(runtime)))
+        }
+    }
+}
+
+// Checks that the runtime is multi-threaded, as blocking reads wait for
+// it to fetch data, which a current-thread runtime can do only while its
+// own thread is given to it
+fn multi_thread(runtime: Handle) -> Result<Handle, InitError> {
+    match runtime.runtime_flavor() {
+        RuntimeFlavor::MultiThread => Ok(runtime),
+        _ => Err(InitError::CurrentThreadRuntime)
+    }
+}
+
 struct FileChecker;
 
 impl ExistsChecker for FileChecker {
-    fn exists<T: AsRef<str>>(&mut self, path: T) -> bool {
-        Path::new(path.as_ref()).is_file()
+    fn exists<T: AsRef<str>>(&mut self, path: T) -> Result<bool, std::io::Error> {
+        Ok(Path::new(path.as_ref()).is_file())
     }
 }
 
 struct S3Checker {
     bucket: Bucket,
-    runtime: Arc<Runtime>
+    runtime: Handle
 }
 
 impl S3Checker {
     fn new(
         url: &Url,
-        runtime: Arc<Runtime>
+        s3_options: &S3Options,
+        runtime: Handle
     ) -> Result<Self, OpenError> {
         let name = url.host_str()
             .ok_or(OpenError::BadPath(url.to_string()))?;
 
-        let bucket = *Bucket::new(
-            name,
-            Region::UsEast1,
-            Credentials::anonymous().unwrap()
-        )
-        .map_err(std::io::Error::other)
-        .map_err(OpenError::from)
-        .map_err(|e| e.with_path(url))?;
+        let bucket = s3_options.bucket(name)
+            .map_err(OpenError::from)
+            .map_err(|e| e.with_path(url))?;
 
         Ok(Self { bucket, runtime })
     }
 }
 
 impl ExistsChecker for S3Checker {
-    fn exists<T: AsRef<str>>(&mut self, path: T) -> bool {
-        Url::parse(path.as_ref())
-            .map(|url|
-                self.runtime.block_on(self.bucket.head_object(url.path()))
-                    .is_ok_and(|(_, code)| code == 200)
-            )
-            .unwrap_or(false)
+    // We check individually when we may not list the bucket, and then S3
+    // reports missing keys as forbidden, so forbidden is taken as missing;
+    // other failures are errors, as a segment may exist despite them.
+    fn exists<T: AsRef<str>>(&mut self, path: T) -> Result<bool, std::io::Error> {
+        let url = Url::parse(path.as_ref())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+        match block_on(&self.runtime, self.bucket.head_object(url.path())) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let e = s3_error(e);
+                match e.kind() {
+                    std::io::ErrorKind::NotFound |
+                    std::io::ErrorKind::PermissionDenied => Ok(false),
+                    _ => Err(e)
+                }
+            }
+        }
+    }
+}
+
+// Finds the segments with one listing of the keys sharing the prototype's
+// base name, rather than a HEAD request for each possible segment
+fn list_s3_segment_paths(
+    url: &Url,
+    example_segment_path: &str,
+    s3_options: &S3Options,
+    runtime: &Handle
+) -> Result<Vec<String>, OpenError>
+{
+    let name = url.host_str()
+        .ok_or(OpenError::BadPath(url.to_string()))?;
+
+    let prefix = url.path()
+        .trim_start_matches('/')
+        .rsplit_once('.')
+        .map(|(base, _)| format!("{base}."))
+        .ok_or(UnrecognizedExtension(example_segment_path.into()))?;
+
+    let bucket = s3_options.bucket(name)
+        .map_err(OpenError::from)
+        .map_err(|e| e.with_path(url))?;
+
+    let results = block_on(runtime, bucket.list(prefix, None))
+        .map_err(std::io::Error::other)
+        .map_err(OpenError::from)
+        .map_err(|e| e.with_path(url))?;
+
+    let listing = results.iter()
+        .flat_map(|r| &r.contents)
+        .map(|o| format!("s3://{name}/{}", o.key));
+
+    Ok(listed_segment_paths(example_segment_path, listing)?)
+}
+
+// Returns the URL with its path replaced, keeping its query
+fn url_with_path(url: &Url, path: &str) -> Url {
+    let mut url = url.clone();
+    url.set_path(path);
+    url
+}
+
+// Checks for the paths on the server of the given URL
+struct HttpChecker {
+    client: reqwest::Client,
+    url: Url,
+    runtime: Handle
+}
+
+impl ExistsChecker for HttpChecker {
+    fn exists<T: AsRef<str>>(&mut self, path: T) -> Result<bool, std::io::Error> {
+        let url = url_with_path(&self.url, path.as_ref());
+        let resp = block_on(&self.runtime, self.client.head(url).send())
+            .map_err(request_error)?;
+
+        match resp.status() {
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            _ => resp.error_for_status()
+                .map(|_| true)
+                .map_err(request_error)
+        }
     }
 }
 
@@ -567,15 +1184,92 @@ impl E01Reader {
         example_segment_path: T,
         options: &E01ReaderOptions
     ) -> Result<Self, OpenError>
+    {
+        let (runtime, owned_runtime) = runtime_for(options)?;
+
+        Self::open_glob_impl(
+            example_segment_path,
+            options,
+            runtime,
+            owned_runtime
+        )
+    }
+
+    pub fn open<T: IntoIterator<Item: AsRef<str>>>(
+        segment_paths: T,
+        options: &E01ReaderOptions
+    ) -> Result<Self, OpenError>
+    {
+        let (runtime, owned_runtime) = runtime_for(options)?;
+
+        Self::open_impl(
+            segment_paths,
+            options,
+            OnceLock::new(),
+            runtime,
+            owned_runtime
+        )
+    }
+
+    // Like open_glob, but uses the given runtime, or the current one if none
+    // is given. Opening does blocking I/O, so it is done on the runtime's
+    // blocking threads.
+    pub async fn open_glob_async<T: AsRef<str>>(
+        example_segment_path: T,
+        options: &E01ReaderOptions
+    ) -> Result<Self, OpenError>
+    {
+        let example_segment_path = example_segment_path.as_ref().to_string();
+        let options = options.clone();
+        let runtime = multi_thread(
+            options.runtime.as_ref()
+                .map_or_else(Handle::current, Handle::clone)
+        )?;
+
+        tokio::task::spawn_blocking(move ||
+            Self::open_glob_impl(
+                example_segment_path,
+                &options,
+                runtime,
+                None
+            )
+        )
+        .await
+        .map_err(InitError::OpenTaskFailed)?
+    }
+
+    // Like open, but uses the given runtime, or the current one
+    pub async fn open_async<T: IntoIterator<Item: AsRef<str>>>(
+        segment_paths: T,
+        options: &E01ReaderOptions
+    ) -> Result<Self, OpenError>
+    {
+        let segment_paths = segment_paths.into_iter()
+            .map(|p| p.as_ref().to_string())
+            .collect::<Vec<_>>();
+        let options = options.clone();
+        let runtime = multi_thread(
+            options.runtime.as_ref()
+                .map_or_else(Handle::current, Handle::clone)
+        )?;
+
+        tokio::task::spawn_blocking(move ||
+            Self::open_impl(segment_paths, &options, OnceLock::new(), runtime, None)
+        )
+        .await
+        .map_err(InitError::OpenTaskFailed)?
+    }
+
+    fn open_glob_impl<T: AsRef<str>>(
+        example_segment_path: T,
+        options: &E01ReaderOptions,
+        runtime: Handle,
+        owned_runtime: Option<Runtime>
+    ) -> Result<Self, OpenError>
     {
         let url = path_or_url_to_url(&example_segment_path)
             .ok_or(OpenError::BadPath(example_segment_path.as_ref().into()))?;
 
-        let runtime = Arc::new(
-            tokio::runtime::Runtime::new()
-                .map_err(InitError::TokioRuntimeFailed)?
-        );
-
         match url.scheme() {
             "file" => Self::open_impl(
                 validated_segment_paths(
@@ -583,74 +1277,157 @@ impl E01Reader {
                     FileChecker,
                 )?,
                 options,
-                runtime
-            ),
-            "s3" => Self::open_impl(
-                validated_segment_paths(
-                    example_segment_path,
-                    S3Checker::new(&url, runtime.clone())?
-                )?,
-                options,
-                runtime
+                OnceLock::new(),
+                runtime,
+                owned_runtime
             ),
+            "s3" => {
+                let s3_options = options.s3.clone().unwrap_or_default();
+
+                let segment_paths = match list_s3_segment_paths(
+                    &url,
+                    example_segment_path.as_ref(),
+                    &s3_options,
+                    &runtime
+                ) {
+                    Ok(segment_paths) => segment_paths,
+                    Err(e) => {
+                        // listing may not be permitted; fall back to
+                        // checking for each segment in turn
+                        warn!("listing segments failed, checking individually: {e}");
+
+                        validated_segment_paths(
+                            example_segment_path,
+                            S3Checker::new(&url, &s3_options, runtime.clone())?
+                        )?
+                    }
+                };
+
+                Self::open_impl(
+                    segment_paths,
+                    options,
+                    OnceLock::new(),
+                    runtime,
+                    owned_runtime
+                )
+            },
+            // Segments are found by changing the extension of the URL's
+            // path, keeping its query, e.g., an access token. URLs which
+            // differ in other ways, e.g., presigned URLs, whose signatures
+            // cover the path, must be given to open instead.
+            "http" | "https" => {
+                let client = reqwest::Client::new();
+
+                let segment_paths = validated_segment_paths(
+                    url.path(),
+                    HttpChecker {
+                        client: client.clone(),
+                        url: url.clone(),
+                        runtime: runtime.clone()
+                    }
+                )?
+                .into_iter()
+                .map(|p| url_with_path(&url, &p).to_string())
+                .collect::<Vec<_>>();
+
+                Self::open_impl(
+                    segment_paths,
+                    options,
+                    OnceLock::from(client),
+                    runtime,
+                    owned_runtime
+                )
+            },
             _ => Err(OpenError::UnsupportedScheme(url.to_string()))
         }
     }
 
-    pub fn open<T: IntoIterator<Item: AsRef<str>>>(
+    fn open_impl<T: IntoIterator<Item: AsRef<str>>>(
         segment_paths: T,
+        options: &E01ReaderOptions,
+        http: OnceLock<reqwest::Client>,
+        runtime: Handle,
+        owned_runtime: Option<Runtime>
+    ) -> Result<Self, OpenError>
+    {
+        let segment_paths = segment_paths.into_iter()
+            .map(|p| p.as_ref().to_string())
+            .collect::<Vec<_>>();
+
+        let files = Arc::new(FilePool::new(options.max_open_files));
+
+        let sources = segment_paths.into_par_iter()
+            .map(|sp| {
+                let src = source_for_path(&sp, options, &files, &http, &runtime)?;
+                Ok((sp, src))
+            })
+            .collect::<Result<Vec<_>, OpenError>>()?;
+
+        Self::open_sources_impl(
+            sources,
+            options,
+            runtime,
+            owned_runtime
+        )
+    }
+
+    // Opens segments from the given sources, in segment order, so that
+    // segments may be read from storage other than files and S3. Segments
+    // are named "<source N>" in errors and segment_paths.
+    pub fn open_sources(
+        sources: Vec<Box<dyn BytesSource + Send>>,
         options: &E01ReaderOptions
     ) -> Result<Self, OpenError>
     {
-        let runtime = Arc::new(
-            tokio::runtime::Runtime::new()
-                .map_err(InitError::TokioRuntimeFailed)?
-        );
+        let (runtime, owned_runtime) = runtime_for(options)?;
+
+        let sources = sources.into_iter()
+            .enumerate()
+            .map(|(idx, src)| (format!("<source {idx}>"), src))
+            .collect::<Vec<_>>();
 
-        Self::open_impl(segment_paths, options, runtime)
+        Self::open_sources_impl(sources, options, runtime, owned_runtime)
     }
 
-    fn open_impl<T: IntoIterator<Item: AsRef<str>>>(
-        segment_paths: T,
+    fn open_sources_impl(
+        sources: Vec<(String, Box<dyn BytesSource + Send>)>,
         options: &E01ReaderOptions,
-        runtime: Arc<Runtime>
+        runtime: Handle,
+        owned_runtime: Option<Runtime>
     ) -> Result<Self, OpenError>
     {
-        let mut sp_itr = segment_paths.into_iter().peekable();
+        if sources.is_empty() {
+            return Err(OpenError::NoSegmentFiles);
+        }
 
 //        let c = DummyCache::new();
 
-        let cache_disk_size = match sp_itr.peek() {
-            Some(p) if p.as_ref().starts_with("s3://") => 256,
-            Some(_) => 0,
-            None => return Err(OpenError::NoSegmentFiles)
-        };
-
-        let cache_chunk_size = 1024 * 1024;
-        let cache_mem_size = 1024;
-        let c = runtime.block_on(
+        let cache_options = options.cache.clone().unwrap_or_default();
+        let c = block_on(
+            &runtime,
             FoyerCache::with_default_cache(
-                cache_chunk_size,
-                cache_mem_size,
-                cache_disk_size,
-                0
+                cache_options.block_size,
+                cache_options.mem_size,
+                cache_options.disk_size,
+                cache_options.readahead,
+                cache_options.dir.as_deref()
             )
         )
         .map_err(InitError::CacheSetupFailed)?;
 
-        let cache = Arc::new(Mutex::new(c));
+        let cache: Arc<dyn Cache + Send + Sync> = Arc::new(c);
 
         let ignore_checksums = options.corrupt_section_policy == CorruptSectionPolicy::DamnTheTorpedoes;
 
         // read the segment metadata
-        let segs = sp_itr.map(|p| p.as_ref().to_string())
-            .collect::<Vec<_>>()
+        let segs = sources
 //            .into_iter()
             .into_par_iter()
             .enumerate()
-            .map(|(idx, sp)| {
+            .map(|(idx, (sp, src))| {
                 let io = make_bytes_reader(
                     &sp,
+                    src,
                     idx,
                     cache.clone(),
                     runtime.clone()
@@ -660,7 +1437,7 @@ impl E01Reader {
             .collect::<Result<Vec<SegmentComponents>, _>>()?;
 
         // process segment metadata
-        let meta = process_segments(segs, ignore_checksums)?;
+        let mut meta = process_segments(segs, ignore_checksums)?;
 
         let exp_chunk_count = meta.volume.chunk_count as usize;
         let chunk_count = meta.chunks.len();
@@ -677,6 +1454,8 @@ impl E01Reader {
         let sector_size = meta.volume.bytes_per_sector as usize;
         let image_size = meta.volume.max_offset() as u64;
 
+        resolve_overflowed_chunks(&mut meta.chunks, chunk_size, image_size);
+
         Ok(Self {
             segments: meta.segments,
             chunks: meta.chunks,
@@ -687,17 +1466,169 @@ impl E01Reader {
             image_size,
             stored_md5: meta.md5,
             stored_sha1: meta.sha1,
+            stored_hashes: meta.hashes,
+            case_metadata: meta.case_metadata,
+            media_info: meta.volume.media_info,
+            acquisition_errors: meta.acquisition_errors,
+            sessions: Session::from_entries(
+                &meta.sessions,
+                sector_count as u64
+            ),
+            table_copies: meta.table_copies,
+            ltree: meta.ltree,
             segment_paths: meta.segment_paths,
             corrupt_section_policy: options.corrupt_section_policy,
             corrupt_chunk_policy: options.corrupt_chunk_policy,
-            workers: vec![],
+            workers: Mutex::new(vec![]),
+            max_workers: options.read_parallelism
+                .unwrap_or_else(rayon::current_num_threads)
+                .max(1),
+            chunk_cache: (cache_options.chunk_cache_size > 0).then(||
+                Mutex::new(ChunkCache::new(cache_options.chunk_cache_size))
+            ),
             cache,
-            runtime
+            runtime,
+            _owned_runtime: owned_runtime
         })
     }
 
+    // hits and misses of the decompressed chunk cache; all 0 if disabled
+    pub fn chunk_cache_stats(&self) -> ChunkCacheStats {
+        self.chunk_cache.as_ref()
+            .map(|cc| cc.lock().expect("poisoned").stats())
+            .unwrap_or_default()
+    }
+
+    // (first sector, sector count) of sectors which could not be read
+    // during acquisition and so are zero-filled in the image
+    pub fn acquisition_errors(&self) -> &[(u64, u64)] {
+        &self.acquisition_errors
+    }
+
+    pub fn write_ddrescue_mapfile<W: Write>(
+        &self,
+        out: W
+    ) -> std::io::Result<()>
+    {
+        write_ddrescue_mapfile(
+            out,
+            &self.acquisition_errors,
+            self.sector_size as u64,
+            self.image_size
+        )
+    }
+
+    // sessions and tracks, for optical media
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    pub fn session_reader(
+        &self,
+        index: usize
+    ) -> Option<SectorRangeReader<'_>>
+    {
+        let s = self.sessions.get(index)?;
+        let (start_sector, sector_count) = (s.start_sector, s.sector_count);
+        // sessions are clamped to the media, so cannot overflow
+        SectorRangeReader::new(self, start_sector, sector_count).ok()
+    }
+
+    // a Read + Seek view of the whole image
+    pub fn cursor(&self) -> E01Cursor<'_> {
+        E01Cursor::new(self)
+    }
+
+    // Streams the chunks of the given range of the image, in order, while
+    // reading ahead twice as many chunks as a read decompresses at once.
+    pub fn stream_chunks(self: &Arc<Self>, range: Range<u64>) -> ChunkStream {
+        ChunkStream::new(self.clone(), range, 2 * self.max_workers)
+    }
+
+    // which copy of each chunk table was used, in order
+    pub fn table_copies(&self) -> &[TableCopy] {
+        &self.table_copies
+    }
+
+    pub(crate) fn take_ltree(&mut self) -> Option<String> {
+        self.ltree.take()
+    }
+
     pub fn read_at_offset(
         &mut self,
+        offset: u64,
+        buf: &mut [u8]
+    ) -> Result<usize, ReadError>
+    {
+        self.read_at(offset, buf)
+    }
+
+    fn read_cached_chunk(
+        &self,
+        chunk_index: usize,
+        beg_in_chunk: usize,
+        buf: &mut [u8]
+    ) -> bool
+    {
+        self.chunk_cache.as_ref()
+            .is_some_and(|cc| cc.lock()
+                .expect("poisoned")
+                .read(chunk_index, beg_in_chunk, buf)
+            )
+    }
+
+    // Reads part of a chunk with the worker. If the chunk cache is enabled
+    // and only part of the chunk is wanted, the whole chunk is decompressed
+    // and cached, as small reads tend to be repeated; whole chunks are
+    // usually read sequentially, and not again.
+    #[allow(clippy::too_many_arguments)]
+    fn read_chunk<WS: WorkerSource>(
+        &self,
+        worker: &mut ReadWorker,
+        chunk: &Chunk,
+        compr_method: CompressionMethod,
+        src: &mut WS,
+        chunk_index: usize,
+        buf: &mut [u8],
+        beg_in_chunk: usize,
+        end_in_chunk: usize
+    ) -> Result<(), ReadErrorKind>
+    {
+        let chunk_beg = chunk_index as u64 * self.chunk_size as u64;
+        let chunk_len = (self.image_size - chunk_beg)
+            .min(self.chunk_size as u64) as usize;
+
+        match &self.chunk_cache {
+            Some(cc) if end_in_chunk - beg_in_chunk < chunk_len => {
+                let ch = worker.read_whole(
+                    chunk,
+                    compr_method,
+                    src,
+                    chunk_index,
+                    chunk_len
+                )?;
+
+                buf.copy_from_slice(&ch[beg_in_chunk..end_in_chunk]);
+                if let Some(spare) = cc.lock().expect("poisoned").insert(chunk_index, ch) {
+                    worker.replace_scratch(spare);
+                }
+                Ok(())
+            },
+            _ => worker.read(
+                chunk,
+                compr_method,
+                src,
+                chunk_index,
+                buf,
+                beg_in_chunk,
+                end_in_chunk
+            )
+        }
+    }
+
+    // Reads may be made concurrently, e.g., through an Arc<E01Reader>
+    pub fn read_at(
+        &self,
         mut offset: u64,
         mut buf: &mut [u8]
     ) -> Result<usize, ReadError>
@@ -721,21 +1652,8 @@ impl E01Reader {
         let beg_chunk_index = (buf_beg / chunk_size) as usize;
         let end_chunk_index = (buf_end / chunk_size + (buf_end % chunk_size).min(1)) as usize;
 
-// TODO: Number of workers should have some fixed/configured maximum,
-// should not scale with the number of chunks to be fetched.
-        if end_chunk_index - beg_chunk_index > self.workers.len() {
-            self.workers.resize(
-                end_chunk_index - beg_chunk_index,
-                ReadWorker::new(
-                    self.chunk_size,
-                    image_end,
-                    self.corrupt_chunk_policy
-                )
-            );
-        }
-
-        let mut tasks = Vec::with_capacity(end_chunk_index - beg_chunk_index);
-        let mut w = &mut self.workers[..];
+        // the chunks which aren't cached
+        let mut chunk_tasks = Vec::with_capacity(end_chunk_index - beg_chunk_index);
 
         while offset < buf_end {
             // get the next chunk
@@ -756,8 +1674,12 @@ impl E01Reader {
             let (bleft, bright) = buf.split_at_mut((end_in_buf - beg_in_buf) as usize);
             buf = bright;
 
-            let (wleft, wright) = w.split_at_mut(1);
-            w = wright;
+            offset += end_in_buf - beg_in_buf;
+
+            // cached chunks need no worker
+            if self.read_cached_chunk(chunk_index, beg_in_chunk, bleft) {
+                continue;
+            }
 
             let src = CacheWorkerSource {
                 cache: self.cache.clone(),
@@ -765,34 +1687,231 @@ impl E01Reader {
                 idx: chunk.segment
             };
 
-            tasks.push((
+            chunk_tasks.push((
                 chunk_index,
                 chunk,
+                seg.compr_method,
                 src,
                 bleft,
                 beg_in_chunk,
                 end_in_chunk,
-                &seg.path,
-                &mut wleft[0]
+                &seg.path
             ));
+        }
 
-            offset += end_in_buf - beg_in_buf;
+        let worker_count = chunk_tasks.len().min(self.max_workers);
+        if worker_count == 0 {
+            return Ok((offset - buf_beg) as usize);
         }
 
-//        tasks.into_iter()
-        tasks.into_par_iter()
-            .try_for_each(|(chunk_index, chunk, mut src, sbuf, beg_in_chunk, end_in_chunk, seg_path, worker)| {
-                worker.read(
-                    chunk,
-                    &mut src,
-                    chunk_index,
-                    sbuf,
-                    beg_in_chunk,
-                    end_in_chunk
-                )
-                .map_err(ReadError::from)
-                .map_err(|e| e.with_path(seg_path))
-            })?;
+        // take idle workers from the pool, making more if there are too few
+        let mut workers = {
+            let mut pool = self.workers.lock().expect("poisoned");
+            let keep = pool.len().saturating_sub(worker_count);
+            pool.split_off(keep)
+        };
+
+        workers.resize_with(
+            worker_count,
+            || ReadWorker::new(
+                self.chunk_size,
+                image_end,
+                self.corrupt_chunk_policy
+            )
+        );
+
+        // the chunks are dealt out to the workers in turn, so that each
+        // reads every worker_count-th chunk
+        let mut tasks = (0..worker_count)
+            .map(|_| vec![])
+            .collect::<Vec<_>>();
+
+        for (i, task) in chunk_tasks.into_iter().enumerate() {
+            tasks[i % worker_count].push(task);
+        }
+
+        let result = workers.par_iter_mut()
+            .zip(tasks)
+            .try_for_each(|(worker, tasks)| tasks.into_iter()
+                .try_for_each(|(chunk_index, chunk, compr_method, mut src, sbuf, beg_in_chunk, end_in_chunk, seg_path)| {
+                    self.read_chunk(
+                        worker,
+                        chunk,
+                        compr_method,
+                        &mut src,
+                        chunk_index,
+                        sbuf,
+                        beg_in_chunk,
+                        end_in_chunk
+                    )
+                    .map_err(ReadError::from)
+                    .map_err(|e| e.with_path(seg_path))
+                })
+            );
+
+        // return the workers to the pool
+        self.workers.lock().expect("poisoned").extend(workers);
+
+        result?;
+
+        Ok((offset - buf_beg) as usize)
+    }
+
+    // Like read_at, but fetches chunk data on the caller's runtime instead
+    // of blocking. Chunks are read one at a time, and decompressed on the
+    // runtime's blocking threads.
+    pub async fn read_at_async(
+        &self,
+        mut offset: u64,
+        mut buf: &mut [u8]
+    ) -> Result<usize, ReadError>
+    {
+        // don't start reading past the end
+        let image_end = self.image_size;
+        if offset > image_end {
+            return Err(ReadErrorKind::OffsetBeyondEnd(offset, image_end))?;
+        }
+
+        // limit the buffer to the image end
+        if offset + buf.len() as u64 > image_end {
+            buf = &mut buf[..(image_end - offset) as usize];
+        }
+
+        let buf_beg = offset;
+        let buf_end = offset + buf.len() as u64;
+
+        let chunk_size = self.chunk_size as u64;
+
+        // a worker is taken from the pool once a chunk isn't cached
+        let mut worker = None;
+
+        let mut result = Ok(());
+
+        while offset < buf_end {
+            // get the next chunk
+            let chunk_index = (offset / chunk_size) as usize;
+
+            let chunk = &self.chunks[chunk_index];
+            let seg = &self.segments[chunk.segment];
+
+            let chunk_beg = chunk_index as u64 * chunk_size;
+            let chunk_end = std::cmp::min(chunk_beg + chunk_size, image_end);
+
+            let beg_in_chunk = (offset - chunk_beg) as usize;
+            let end_in_chunk = (std::cmp::min(chunk_end, buf_end) - chunk_beg) as usize;
+
+            let beg_in_buf = (offset - buf_beg) as usize;
+            let end_in_buf = beg_in_buf + (end_in_chunk - beg_in_chunk);
+
+            // cached chunks need no fetching
+            if self.read_cached_chunk(
+                chunk_index,
+                beg_in_chunk,
+                &mut buf[beg_in_buf..end_in_buf]
+            )
+            {
+                offset += (end_in_buf - beg_in_buf) as u64;
+                continue;
+            }
+
+            // fetch the chunk data; pattern chunks have none
+            let mut src = BufWorkerSource {
+                off: chunk.data_offset,
+                buf: vec![]
+            };
+
+            let r = if chunk.pattern.is_none() {
+                src.buf.resize((chunk.end_offset - chunk.data_offset) as usize, 0);
+
+                self.cache
+                    .read(chunk.segment, chunk.data_offset, &mut src.buf)
+                    .await
+            }
+            else {
+                Ok(())
+            };
+
+            let r = match r {
+                Ok(()) => {
+                    // decompress the whole chunk if it is to be cached
+                    let chunk_len = (chunk_end - chunk_beg) as usize;
+                    let whole = self.chunk_cache.is_some()
+                        && end_in_chunk - beg_in_chunk < chunk_len;
+                    let (beg, end) = if whole {
+                        (0, chunk_len)
+                    }
+                    else {
+                        (beg_in_chunk, end_in_chunk)
+                    };
+
+                    let chunk = chunk.clone();
+                    let compr_method = seg.compr_method;
+
+                    let mut w = worker.take().unwrap_or_else(|| {
+                        let popped = self.workers.lock().expect("poisoned").pop();
+                        popped.unwrap_or_else(|| ReadWorker::new(
+                            self.chunk_size,
+                            image_end,
+                            self.corrupt_chunk_policy
+                        ))
+                    });
+
+                    let (mut w, r) = tokio::task::spawn_blocking(move || {
+                        let r = if whole {
+                            w.read_whole(&chunk, compr_method, &mut src, chunk_index, end)
+                        }
+                        else {
+                            let mut out = vec![0; end - beg];
+                            w.read(
+                                &chunk,
+                                compr_method,
+                                &mut src,
+                                chunk_index,
+                                &mut out,
+                                beg,
+                                end
+                            )
+                            .map(|_| out)
+                        };
+                        (w, r)
+                    })
+                    .await
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+
+                    let r = r.map(|out| match &self.chunk_cache {
+                        Some(cc) if whole => {
+                            buf[beg_in_buf..end_in_buf]
+                                .copy_from_slice(&out[beg_in_chunk..end_in_chunk]);
+                            let spare = cc.lock().expect("poisoned").insert(chunk_index, out);
+                            if let Some(spare) = spare {
+                                w.replace_scratch(spare);
+                            }
+                        },
+                        _ => buf[beg_in_buf..end_in_buf].copy_from_slice(&out)
+                    });
+
+                    worker = Some(w);
+                    r
+                },
+                Err(e) => Err(ReadErrorKind::from(e))
+            }
+            .map_err(ReadError::from)
+            .map_err(|e| e.with_path(&seg.path));
+
+            if r.is_err() {
+                result = r;
+                break;
+            }
+
+            offset += (end_in_buf - beg_in_buf) as u64;
+        }
+
+        // return the worker to the pool
+        if let Some(worker) = worker {
+            self.workers.lock().expect("poisoned").push(worker);
+        }
+
+        result?;
 
         Ok((offset - buf_beg) as usize)
     }
@@ -800,4 +1919,123 @@ impl E01Reader {
 
 #[cfg(test)]
 mod test {
+    use super::*;
+
+    use md5::{Digest, Md5};
+
+    use crate::test_data::IMAGE_E01;
+
+    #[test]
+    fn read_at_bounded_workers() {
+        let reader = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &E01ReaderOptions {
+                read_parallelism: Some(2),
+                cache: Some(CacheOptions {
+                    chunk_cache_size: 0,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+        ).unwrap();
+
+        // read the whole image at once
+        let mut buf = vec![0; IMAGE_E01.image_size as usize];
+        assert_eq!(reader.read_at(0, &mut buf).unwrap(), buf.len());
+
+        assert_eq!(
+            hex::encode(Md5::digest(&buf)),
+            IMAGE_E01.md5.unwrap()
+        );
+
+        assert_eq!(reader.workers.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn read_at_cached_needs_no_workers() {
+        let reader = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &E01ReaderOptions {
+                cache: Some(CacheOptions {
+                    chunk_cache_size: 1024 * 1024,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+        ).unwrap();
+
+        let mut exp = vec![0; 512];
+        reader.read_at(1000, &mut exp).unwrap();
+
+        // hits take no worker from the pool, nor make one
+        reader.workers.lock().unwrap().clear();
+
+        let mut act = vec![0; 512];
+        reader.read_at(1000, &mut act).unwrap();
+        assert_eq!(act, exp);
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        act.fill(0);
+        runtime.block_on(reader.read_at_async(1000, &mut act)).unwrap();
+        assert_eq!(act, exp);
+
+        assert!(reader.workers.lock().unwrap().is_empty());
+        assert_eq!(reader.chunk_cache_stats().hits, 2);
+    }
+
+    #[test]
+    fn s3_options_bucket_custom_endpoint() {
+        let options = S3Options {
+            region: None,
+            endpoint: Some("http://127.0.0.1:9000".into()),
+            path_style: true,
+            credentials: S3Credentials::Explicit {
+                access_key: "minioadmin".into(),
+                secret_key: "minioadmin".into(),
+                session_token: None
+            }
+        };
+
+        let bucket = options.bucket("evidence").unwrap();
+        assert!(bucket.is_path_style());
+        assert_eq!(bucket.region().endpoint(), "http://127.0.0.1:9000");
+    }
+
+    #[test]
+    fn s3_options_bucket_default() {
+        let bucket = S3Options::default().bucket("evidence").unwrap();
+        assert!(!bucket.is_path_style());
+        assert_eq!(bucket.region().endpoint(), Region::UsEast1.endpoint());
+    }
+
+    fn chunk(data_offset: u64, end_offset: u64, compression_unknown: bool) -> Chunk {
+        Chunk {
+            segment: 0,
+            data_offset,
+            end_offset,
+            compressed: false,
+            has_checksum: true,
+            pattern: None,
+            compression_unknown
+        }
+    }
+
+    #[test]
+    fn resolve_overflowed_chunks_by_size() {
+        let mut chunks = [
+            chunk(0, 100, false),
+            chunk(100, 200, true),
+            chunk(200, 200 + 1024 + 4, true),
+            // the last chunk is short
+            chunk(1228, 1228 + 512 + 4, true)
+        ];
+
+        resolve_overflowed_chunks(&mut chunks, 1024, 3 * 1024 + 512);
+
+        assert!(!chunks[0].compressed);
+        assert!(chunks[1].compressed && !chunks[1].has_checksum);
+        assert!(!chunks[2].compressed && chunks[2].has_checksum);
+        assert!(!chunks[3].compressed && chunks[3].has_checksum);
+        assert!(chunks.iter().all(|c| !c.compression_unknown));
+    }
 }
@@ -23,5 +23,11 @@ pub enum LibError {
     #[error("Unknown compression method value: {0}")]
     UnknownCompressionMethod(u16),
     #[error("Invalid segment file header")]
-    InvalidSegmentFileHeader
+    InvalidSegmentFileHeader,
+    #[error("Decompression of {0} section failed: {1}")]
+    DecompressionFailed(&'static str, std::io::Error),
+    #[error("Malformed {0} section: {1}")]
+    MalformedSection(&'static str, String),
+    #[error("Section type {0:#x} is encrypted, which is unsupported")]
+    EncryptedSection(u32)
 }
@@ -0,0 +1,199 @@
+use std::{
+    fs::File,
+    sync::{Arc, Mutex}
+};
+use tracing::debug;
+
+// the most files to keep open if no limit is given
+const DEFAULT_MAX_OPEN: usize = 256;
+
+// A bounded set of open files, shared by the FileSources of a reader, so
+// that segments aren't reopened for every read. The least recently used
+// file is closed to make room for another.
+#[derive(Debug)]
+pub struct FilePool {
+    max_open: usize,
+    // most recently used last
+    files: Mutex<Vec<(String, Arc<File>)>>
+}
+
+impl FilePool {
+    // The pool keeps at most max_open files open, and never more than half
+    // the process's limit on open files.
+    pub fn new(max_open: Option<usize>) -> Self {
+        let max_open = max_open.unwrap_or(DEFAULT_MAX_OPEN);
+        let max_open = match fd_limit() {
+            Some(limit) => max_open.min(limit / 2),
+            None => max_open
+        }
+        .max(1);
+
+        Self {
+            max_open,
+            files: Mutex::new(vec![])
+        }
+    }
+
+    // Returns the open file for the path, opening it if need be. A file
+    // closed by the pool stays open until reads already using it finish.
+    pub fn get(&self, path: &str) -> Result<Arc<File>, std::io::Error> {
+        if let Some(f) = touch(&mut self.files.lock().expect("poisoned"), path) {
+            return Ok(f);
+        }
+
+        // don't hold the lock while opening, as opening can be slow on
+        // network file systems
+        let f = Arc::new(File::open(path)?);
+
+        let mut files = self.files.lock().expect("poisoned");
+
+        // another read may have opened the file meanwhile
+        if let Some(f) = touch(&mut files, path) {
+            return Ok(f);
+        }
+
+        if files.len() >= self.max_open {
+            let (p, _) = files.remove(0);
+            debug!("closing {p}");
+        }
+
+        files.push((path.into(), f.clone()));
+        Ok(f)
+    }
+}
+
+// Returns the file for the path if it is open, marking it as the most
+// recently used.
+fn touch(
+    files: &mut Vec<(String, Arc<File>)>,
+    path: &str
+) -> Option<Arc<File>>
+{
+    let i = files.iter().position(|(p, _)| p == path)?;
+    let e = files.remove(i);
+    let f = e.1.clone();
+    files.push(e);
+    Some(f)
+}
+
+#[cfg(unix)]
+fn fd_limit() -> Option<usize> {
+    let mut rl = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: rl is a valid rlimit for getrlimit to fill in
+    let r = unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut rl) };
+    (r == 0).then(|| usize::try_from(rl.rlim_cur).unwrap_or(usize::MAX))
+}
+
+#[cfg(not(unix))]
+fn fd_limit() -> Option<usize> {
+    None
+}
+
+// Fills buf from the file at off, without using the file's cursor, so that
+// the file can be read by many threads at once.
+#[cfg(unix)]
+pub fn read_exact_at(
+    f: &File,
+    buf: &mut [u8],
+    off: u64
+) -> Result<(), std::io::Error>
+{
+    std::os::unix::fs::FileExt::read_exact_at(f, buf, off)
+}
+
+#[cfg(windows)]
+pub fn read_exact_at(
+    f: &File,
+    mut buf: &mut [u8],
+    mut off: u64
+) -> Result<(), std::io::Error>
+{
+    use std::os::windows::fs::FileExt;
+
+    while !buf.is_empty() {
+        match f.seek_read(buf, off) {
+            Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => {
+                buf = &mut buf[n..];
+                off += n as u64;
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e)
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Write;
+
+    fn temp_files(n: usize) -> (tempfile::TempDir, Vec<String>) {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = (0..n)
+            .map(|i| {
+                let p = dir.path().join(format!("{i}"));
+                File::create(&p).unwrap()
+                    .write_all(&[i as u8; 16]).unwrap();
+                p.to_str().unwrap().to_string()
+            })
+            .collect();
+        (dir, paths)
+    }
+
+    fn open_paths(pool: &FilePool) -> Vec<String> {
+        pool.files.lock().unwrap()
+            .iter()
+            .map(|(p, _)| p.clone())
+            .collect()
+    }
+
+    #[test]
+    fn get_reuses() {
+        let (_dir, paths) = temp_files(1);
+        let pool = FilePool::new(Some(2));
+
+        let a = pool.get(&paths[0]).unwrap();
+        let b = pool.get(&paths[0]).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+    }
+
+    #[test]
+    fn get_closes_least_recently_used() {
+        let (_dir, paths) = temp_files(3);
+        let pool = FilePool::new(Some(2));
+
+        pool.get(&paths[0]).unwrap();
+        pool.get(&paths[1]).unwrap();
+        pool.get(&paths[0]).unwrap();
+        pool.get(&paths[2]).unwrap();
+
+        assert_eq!(open_paths(&pool), [paths[0].clone(), paths[2].clone()]);
+    }
+
+    #[test]
+    fn get_missing() {
+        let pool = FilePool::new(None);
+        assert!(pool.get("/nonexistent/segment.E01").is_err());
+        assert!(open_paths(&pool).is_empty());
+    }
+
+    #[test]
+    fn read_exact_at_ok() {
+        let (_dir, paths) = temp_files(2);
+        let pool = FilePool::new(None);
+
+        let f = pool.get(&paths[1]).unwrap();
+        let mut buf = [0; 4];
+        read_exact_at(&f, &mut buf, 12).unwrap();
+        assert_eq!(buf, [1; 4]);
+
+        assert_eq!(
+            read_exact_at(&f, &mut buf, 14).unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -1,17 +1,39 @@
 use futures::future::{BoxFuture, FutureExt};
-use std::io::SeekFrom;
-use tokio::{
-    fs::File,
-    io::{AsyncReadExt, AsyncSeekExt}
+use std::{
+    fs::Metadata,
+    sync::Arc,
+    time::UNIX_EPOCH
 };
 use tracing::trace;
 
-use crate::bytessource::BytesSource;
+use crate::{
+    bytessource::BytesSource,
+    filepool::{FilePool, read_exact_at}
+};
 
 #[derive(Clone, Debug)]
 pub struct FileSource {
     pub path: String,
-    pub len: u64
+    pub len: u64,
+    pub version: Option<String>,
+    // the open files shared by the segments of a reader
+    pub files: Arc<FilePool>
+}
+
+// Returns the version of a file, from its modification time and, where
+// there are any, its device and inode, so that replacing it is noticed
+pub fn file_version(meta: &Metadata) -> Option<String> {
+    let mtime = meta.modified().ok()?
+        .duration_since(UNIX_EPOCH).ok()?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some(format!("{}.{:09}:{}:{}", mtime.as_secs(), mtime.subsec_nanos(), meta.dev(), meta.ino()))
+    }
+
+    #[cfg(not(unix))]
+    Some(format!("{}.{:09}", mtime.as_secs(), mtime.subsec_nanos()))
 }
 
 impl BytesSource for FileSource {
@@ -22,18 +44,28 @@ impl BytesSource for FileSource {
     ) -> BoxFuture<'static, Result<Vec<u8>, std::io::Error>>
     {
         let p = self.path.clone();
+        let files = self.files.clone();
 
         async move {
-            let mut r = File::open(p).await?;
-            r.seek(SeekFrom::Start(beg)).await?;
-            let mut buf = vec![0; (end - beg) as usize];
-            r.read_exact(&mut buf[..]).await?;
-            trace!("read [{beg},{end}) from File");
-            Ok(buf)
+            // opening and positional reads block, so do them off the
+            // runtime's worker threads
+            tokio::task::spawn_blocking(move || {
+                let f = files.get(&p)?;
+                let mut buf = vec![0; (end - beg) as usize];
+                read_exact_at(&f, &mut buf, beg)?;
+                trace!("read [{beg},{end}) from File");
+                Ok(buf)
+            })
+            .await
+            .map_err(std::io::Error::other)?
         }.boxed()
     }
 
     fn end(&self) -> u64 {
         self.len
     }
+
+    fn version(&self) -> Option<String> {
+        self.version.clone()
+    }
 }
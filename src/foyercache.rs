@@ -6,55 +6,108 @@ use foyer::{
     FsDeviceBuilder,
     HybridCache,
     HybridCacheBuilder,
-    HybridGetOrFetch
+    HybridCacheEntry,
+    RecoverMode
 };
 use foyer_common::code::HashBuilder;
-use futures::future::{TryFutureExt, try_join_all};
+use futures::future::{BoxFuture, FutureExt, Shared, TryFutureExt, join_all, try_join_all};
+use md5::{Digest, Md5};
 use std::{
+    collections::HashMap,
     fmt::Debug,
-    sync::Arc
+    path::Path,
+    sync::{Arc, LazyLock, Mutex}
 };
 use tempfile::TempDir;
-use tracing::trace;
+use tracing::{debug, trace};
 
 use crate::{
+    blockfetch::{coalesce_blocks, fetch_blocks, readahead_range},
     bytessource::BytesSource,
     cache::Cache,
     placeholdersource::PlaceholderSource
 };
 
+// the most to request from a source at once when fetching adjacent chunks
+const MAX_FETCH_SIZE: u64 = 16 * 1024 * 1024;
+
+// a fetch of some blocks of a source, which completes once they are cached,
+// with their cache entries by offset; it is shared by the reads waiting on it
+type Fetched<S> = Result<
+    Arc<Vec<(u64, HybridCacheEntry<(u64, u64), Vec<u8>, S>)>>,
+    Arc<std::io::Error>
+>;
+type Fetch<S> = Shared<BoxFuture<'static, Fetched<S>>>;
+
+// fetches, by the keys of the blocks each is fetching
+type InFlight<S> = HashMap<(u64, u64), Fetch<S>>;
+
+struct Source {
+    src: Box<dyn BytesSource + Send>,
+    // the key the source's blocks are cached by, which is stable across runs
+    key: u64,
+    // the end of the read-ahead requested so far
+    readahead_end: u64
+}
+
+// Reads may be made concurrently, so the state is locked only briefly, and
+// never while fetching; reads of blocks already being fetched wait for
+// that fetch instead of making their own.
 pub struct FoyerCache<S = DefaultHasher>
 where
     S: HashBuilder + Debug
 {
     chlen: usize,
-    sources: Vec<Box<dyn BytesSource + Send>>,
-    cache: Arc<HybridCache<(usize, u64), Vec<u8>, S>>,
-    cache_dir: TempDir,
+    sources: Mutex<Vec<Source>>,
+    cache: Arc<HybridCache<(u64, u64), Vec<u8>, S>>,
+    in_flight: Arc<Mutex<InFlight<S>>>,
+    // kept so that a temporary cache directory lives as long as the cache
+    cache_dir: Option<TempDir>,
     readahead: usize
 }
 
 impl FoyerCache<DefaultHasher> {
+    // Sizes are in bytes; readahead is in chunks. If a cache directory is
+    // given, the disk cache is kept there and reused by later caches with
+    // the same directory; otherwise, it is in a temporary directory.
     pub async fn with_default_cache(
         chlen: usize,
         mem_size: usize,
         disk_size: usize,
-        readahead: usize
+        readahead: usize,
+        dir: Option<&Path>
     ) -> Result<Self, std::io::Error>
     {
-        let cache_dir = tempfile::tempdir()?;
+        let (cache_dir, path) = match dir {
+            // nothing would be kept there
+            Some(_) if disk_size == 0 => return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "a cache directory requires a disk cache size"
+            )),
+            Some(dir) => {
+                std::fs::create_dir_all(dir)?;
+                (None, dir.to_path_buf())
+            },
+            None => {
+                let cache_dir = tempfile::tempdir()?;
+                let path = cache_dir.path().to_path_buf();
+                (Some(cache_dir), path)
+            }
+        };
 
         let builder = HybridCacheBuilder::new()
             .memory(mem_size)
+            .with_weighter(|_, ch: &Vec<u8>| ch.len())
             .storage();
 
         let builder = if disk_size > 0 {
-            let device = FsDeviceBuilder::new(cache_dir.path())
+            let device = FsDeviceBuilder::new(&path)
                 .with_capacity(disk_size)
                 .build()
                 .map_err(std::io::Error::other)?;
 
             builder.with_engine_config(BlockEngineConfig::new(device))
+                .with_recover_mode(RecoverMode::Quiet)
         }
         else {
             builder
@@ -74,40 +127,137 @@ where
     S: HashBuilder + Debug
 {
     pub fn new(
-        cache: HybridCache<(usize, u64), Vec<u8>, S>,
-        cache_dir: TempDir,
+        cache: HybridCache<(u64, u64), Vec<u8>, S>,
+        cache_dir: Option<TempDir>,
         chlen: usize,
         readahead: usize
     ) -> Self
     {
         Self {
             chlen,
-            sources: vec![],
+            sources: Mutex::new(vec![]),
             cache: Arc::new(cache),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
             cache_dir,
             readahead
         }
     }
+
+    // Fetches the blocks at the given offsets, with one request per run of
+    // adjacent blocks, and caches them. The fetch is registered as in flight
+    // until it completes, and must be registered with the in-flight map
+    // locked, so that no other fetch of the same blocks starts meanwhile.
+    fn fetch(
+        &self,
+        in_flight: &mut InFlight<S>,
+        source: &Source,
+        offsets: &[u64]
+    ) -> Fetch<S>
+    {
+        let end = source.src.end();
+        let ranges = coalesce_blocks(offsets, self.chlen as u64, MAX_FETCH_SIZE, end);
+        let fut = fetch_blocks(source.src.as_ref(), &ranges, self.chlen as u64);
+
+        let key = source.key;
+        let keys = offsets.iter()
+            .map(|choff| (key, *choff))
+            .collect::<Vec<_>>();
+
+        let cache = self.cache.clone();
+        let in_flight_done = self.in_flight.clone();
+
+        let fetch = {
+            let keys = keys.clone();
+            async move {
+                let r = fut.await
+                    .map(|chunks| Arc::new(
+                        chunks.into_iter()
+                            .map(|(choff, ch)| (choff, cache.insert((key, choff), ch)))
+                            .collect::<Vec<_>>()
+                    ))
+                    .map_err(Arc::new);
+
+                let mut in_flight = in_flight_done.lock().expect("poisoned");
+                for k in &keys {
+                    in_flight.remove(k);
+                }
+
+                r
+            }
+        }
+        .boxed()
+        .shared();
+
+        in_flight.extend(keys.into_iter().map(|k| (k, fetch.clone())));
+
+        fetch
+    }
+
+    // Fetches the chunks following a read in the background, in batches of
+    // at least half the read-ahead, so that a sequential reader finds them
+    // already cached.
+    fn read_ahead(&self, idx: usize, source: &mut Source, csend: u64) {
+        let chlen = self.chlen as u64;
+        let rabeg = csend.div_ceil(chlen) * chlen;
+
+        let Some((rabeg, raend)) = readahead_range(
+            rabeg,
+            self.readahead as u64 * chlen,
+            source.readahead_end,
+            source.src.end()
+        )
+        else {
+            return;
+        };
+
+        source.readahead_end = raend;
+
+        let mut in_flight = self.in_flight.lock().expect("poisoned");
+
+        let missing = (rabeg..raend)
+            .step_by(self.chlen)
+            .filter(|choff| !in_flight.contains_key(&(source.key, *choff)))
+            .filter(|choff| !self.cache.contains(&(source.key, *choff)))
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            return;
+        }
+
+        trace!("reading ahead {idx} [{rabeg},{raend})");
+
+        let fetch = self.fetch(&mut in_flight, source, &missing);
+
+        tokio::spawn(async move {
+            if let Err(e) = fetch.await {
+                debug!("read-ahead of {idx} failed: {e}");
+            }
+        });
+    }
 }
 
-fn make_getter<S>(
-    chlen: usize,
-    idx: usize,
-    source: &Box<dyn BytesSource + Send>,
-    end: u64,
-    cache: Arc<HybridCache<(usize, u64), Vec<u8>, S>>
-) -> impl FnMut(u64) -> HybridGetOrFetch<(usize, u64), Vec<u8>, S>
-where
-    S: HashBuilder + Debug
-{
-    move |choff: u64| {
-        let fetch = move ||
-            source.read(choff, (choff + chlen as u64).min(end))
-                .map_err(foyer::Error::io_error);
+// Cached chunks are keyed by their source's name, length, and version,
+// rather than its index, so that a persistent disk cache can be shared by
+// images, but its blocks of a changed source aren't used. Sources with no
+// version get a different key in each process, as they can't be checked.
+fn source_key(name: &str, len: u64, version: Option<&str>) -> u64 {
+    static PROCESS_TOKEN: LazyLock<u64> = LazyLock::new(rand::random);
+
+    let h = Md5::new()
+        .chain_update(name)
+        .chain_update(len.to_le_bytes());
 
-        trace!("fetching {idx} [{choff},{})", (choff + chlen as u64).min(end));
-        cache.get_or_fetch(&(idx, choff), fetch)
+    let h = match version {
+        Some(v) => h.chain_update([1]).chain_update(v),
+        None => h.chain_update([0]).chain_update(PROCESS_TOKEN.to_le_bytes())
     }
+    .finalize();
+
+    u64::from_le_bytes(h[..8].try_into().expect("8 bytes"))
+}
+
+fn fetch_error(e: Arc<std::io::Error>) -> std::io::Error {
+    std::io::Error::new(e.kind(), e)
 }
 
 #[async_trait]
@@ -116,58 +266,110 @@ where
     S: HashBuilder + Debug
 {
     async fn read(
-        &mut self,
+        &self,
         idx: usize,
         off: u64,
         buf: &mut [u8]
     ) -> Result<(), std::io::Error>
     {
-        let source = self.sources.get(idx)
-            .ok_or(std::io::Error::other(format!("{idx} out of bounds")))?;
-
-        let end = source.end();
-
         // cs = chunk source
         let csbeg = (off / self.chlen as u64) * self.chlen as u64;
         let csend = off + buf.len() as u64;
 
-        // ra = read-ahead
-        let rabeg = csend.div_ceil(self.chlen as u64);
-        let raend = (rabeg + (self.readahead * self.chlen) as u64).min(end);
-
-        // request the chunks we need
-        let getter = make_getter(
-            self.chlen,
-            idx,
-            source,
-            end,
-            self.cache.clone()
-        );
+        let key = {
+            let mut sources = self.sources.lock().expect("poisoned");
+            let source = sources.get_mut(idx)
+                .ok_or(std::io::Error::other(format!("{idx} out of bounds")))?;
+
+            self.read_ahead(idx, source, csend);
+            source.key
+        };
 
-        let fut = try_join_all((csbeg..csend)
+        // look up the chunks we need
+        let offsets = (csbeg..csend)
             .step_by(self.chlen)
-            .map(getter)
-        );
+            .collect::<Vec<_>>();
 
-        // request read-ahead chunks in the background
-        let getter = make_getter(
-            self.chlen,
-            idx,
-            source,
-            end,
-            self.cache.clone()
-        );
+        let mut chunks = vec![None; offsets.len()];
+
+        // wait for those being fetched, rather than fetching them again;
+        // any which fail are fetched below
+        let pending = {
+            let in_flight = self.in_flight.lock().expect("poisoned");
+            offsets.iter()
+                .filter_map(|choff| in_flight.get(&(key, *choff)).cloned())
+                .collect::<Vec<_>>()
+        };
+
+        for fetched in join_all(pending).await.into_iter().flatten() {
+            for (choff, ch) in fetched.iter() {
+                if let Some(i) = offsets.iter().position(|o| o == choff) {
+                    chunks[i] = Some(ch.clone());
+                }
+            }
+        }
+
+        // look up the rest
+        let found = try_join_all(
+            offsets.iter()
+                .zip(&chunks)
+                .filter(|(_, ch)| ch.is_none())
+                .map(|(choff, _)| async move {
+                    self.cache.get(&(key, *choff))
+                        .await
+                        .map(|ch| (*choff, ch))
+                })
+        )
+        .await
+        .map_err(std::io::Error::other)?;
+
+        for (choff, ch) in found {
+            let i = ((choff - csbeg) / self.chlen as u64) as usize;
+            chunks[i] = ch;
+        }
+
+        // fetch the missing ones, or wait for other reads fetching them
+        let missing = offsets.iter()
+            .zip(&chunks)
+            .filter(|(_, ch)| ch.is_none())
+            .map(|(choff, _)| *choff)
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            let fetches = {
+                let sources = self.sources.lock().expect("poisoned");
+                let mut in_flight = self.in_flight.lock().expect("poisoned");
+
+                let (theirs, ours): (Vec<_>, Vec<_>) = missing.iter()
+                    .partition(|choff| in_flight.contains_key(&(key, **choff)));
 
-        let _ = (rabeg..raend).step_by(self.chlen)
-            .map(getter)
-            .map(tokio::spawn);
+                let mut fetches = theirs.iter()
+                    .map(|choff| in_flight[&(key, *choff)].clone())
+                    .collect::<Vec<_>>();
 
-        // wait for the chunks we need
-        let chunks = fut.await.map_err(std::io::Error::other)?;
+                if !ours.is_empty() {
+                    fetches.push(self.fetch(&mut in_flight, &sources[idx], &ours));
+                }
+
+                fetches
+            };
+
+            for fetched in try_join_all(fetches.into_iter().map(|f| f.map_err(fetch_error))).await? {
+                for (choff, ch) in fetched.iter() {
+                    if let Some(i) = offsets.iter().position(|o| o == choff) {
+                        chunks[i] = Some(ch.clone());
+                    }
+                }
+            }
+        }
+
+        let chunks = chunks.into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or(std::io::Error::other("missing chunk"))?;
 
         let mut bbeg = 0;
 
-        for (choff, ch) in (csbeg..csend).step_by(self.chlen).zip(chunks) {
+        for (choff, ch) in offsets.into_iter().zip(chunks) {
             trace!("fetched {idx} [{choff},{})", choff + ch.len() as u64);
 
             let chbeg = (off + bbeg) - choff;
@@ -184,15 +386,161 @@ where
     }
 
     fn end(&self, idx: usize) -> Result<u64, std::io::Error> {
-        self.sources.get(idx)
+        self.sources.lock()
+            .expect("poisoned")
+            .get(idx)
             .ok_or(std::io::Error::other(format!("{idx} out of bounds")))
-            .map(|src| src.end())
+            .map(|source| source.src.end())
     }
 
-    fn add_source(&mut self, idx: usize, src: Box<dyn BytesSource + Send>) {
-        if self.sources.len() <= idx {
-            self.sources.resize_with(idx + 1, || Box::new(PlaceholderSource));
+    fn add_source(
+        &self,
+        idx: usize,
+        name: &str,
+        src: Box<dyn BytesSource + Send>
+    )
+    {
+        let mut sources = self.sources.lock().expect("poisoned");
+        if sources.len() <= idx {
+            sources.resize_with(idx + 1, || Source {
+                src: Box::new(PlaceholderSource),
+                key: 0,
+                readahead_end: 0
+            });
         }
-        self.sources[idx] = src;
+        sources[idx] = Source {
+            key: source_key(name, src.end(), src.version().as_deref()),
+            src,
+            readahead_end: 0
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::test_helper::CountingSource;
+
+    // a source of 100 bytes whose requests each take a while
+    fn slow_source(version: Option<&str>) -> CountingSource {
+        CountingSource {
+            delay: Duration::from_millis(50),
+            version: version.map(String::from),
+            ..CountingSource::new(100)
+        }
+    }
+
+    #[test]
+    fn read_waits_for_readahead() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let cache = FoyerCache::with_default_cache(10, 1 << 20, 0, 3, None)
+                .await
+                .unwrap();
+
+            let src = slow_source(None);
+            let requests = src.requests.clone();
+            cache.add_source(0, "src", Box::new(src));
+
+            // the first read starts reading ahead the next three blocks
+            let mut buf = [0; 10];
+            cache.read(0, 0, &mut buf).await.unwrap();
+
+            // the next read waits for the read-ahead
+            cache.read(0, 10, &mut buf).await.unwrap();
+            assert_eq!(buf[0], 10);
+
+            let mut requests = requests.lock().unwrap().clone();
+            requests.sort();
+            assert_eq!(requests, [(0, 10), (10, 40)]);
+        });
+    }
+
+    #[test]
+    fn concurrent_reads_share_fetch() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let cache = FoyerCache::with_default_cache(10, 1 << 20, 0, 0, None)
+                .await
+                .unwrap();
+
+            let src = slow_source(None);
+            let requests = src.requests.clone();
+            cache.add_source(0, "src", Box::new(src));
+
+            // the second read waits for the block the first is fetching,
+            // while the first is not held up by the second
+            let mut buf1 = [0; 10];
+            let mut buf2 = [0; 5];
+            let (r1, r2) = futures::join!(
+                cache.read(0, 20, &mut buf1),
+                cache.read(0, 25, &mut buf2)
+            );
+            r1.unwrap();
+            r2.unwrap();
+
+            assert_eq!(buf1, std::array::from_fn(|i| 20 + i as u8));
+            assert_eq!(buf2, std::array::from_fn(|i| 25 + i as u8));
+            assert_eq!(*requests.lock().unwrap(), [(20, 30)]);
+        });
+    }
+
+    // Reads the first 30 bytes of a source through a cache in dir, returning
+    // the requests made of the source
+    async fn read_through(
+        dir: &Path,
+        version: Option<&str>
+    ) -> Vec<(u64, u64)>
+    {
+        // the disk cache needs room for more than one of its 16 MiB blocks
+        let cache = FoyerCache::with_default_cache(10, 1 << 20, 1 << 26, 0, Some(dir))
+            .await
+            .unwrap();
+
+        let src = slow_source(version);
+        let requests = src.requests.clone();
+        cache.add_source(0, "src", Box::new(src));
+
+        let mut buf = [0; 30];
+        cache.read(0, 0, &mut buf).await.unwrap();
+        assert_eq!(buf, std::array::from_fn(|i| i as u8));
+
+        // write the cached blocks out
+        cache.cache.close().await.unwrap();
+
+        requests.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn reopened_cache_hits() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            assert_eq!(read_through(dir.path(), Some("v1")).await, [(0, 30)]);
+            // the blocks are found on disk
+            assert_eq!(read_through(dir.path(), Some("v1")).await, []);
+            // but not once the source has changed
+            assert_eq!(read_through(dir.path(), Some("v2")).await, [(0, 30)]);
+            // nor if it has no version, and so can't be checked
+            assert_eq!(read_through(dir.path(), None).await, [(0, 30)]);
+        });
+    }
+
+    #[test]
+    fn dir_without_disk_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let r = runtime.block_on(
+            FoyerCache::with_default_cache(10, 1 << 20, 0, 0, Some(dir.path()))
+        );
+
+        assert_eq!(r.err().map(|e| e.kind()), Some(std::io::ErrorKind::InvalidInput));
     }
 }
@@ -0,0 +1,3 @@
+#![allow(unused_imports)]
+#![allow(non_camel_case_types)]
+include!(concat!(env!("OUT_DIR"), "/ewf_ltree_header.rs"));
@@ -11,6 +11,15 @@ pub mod ewf_volume;
 pub mod ewf_volume_smart;
 
 pub mod ewf_table_header;
+pub mod ewf_table_header_v2;
+
+pub mod ewf_error2_header;
+pub mod ewf_error_header_v2;
+
+pub mod ewf_session_header;
+pub mod ewf_session_header_v2;
 
 pub mod ewf_digest_section;
 pub mod ewf_hash_section;
+
+pub mod ewf_ltree_header;
@@ -0,0 +1,145 @@
+use futures::future::{BoxFuture, FutureExt};
+use reqwest::{
+    Client,
+    StatusCode,
+    header::{CONTENT_LENGTH, ETAG, LAST_MODIFIED, RANGE}
+};
+use std::io::ErrorKind;
+use tracing::trace;
+use url::Url;
+
+use crate::bytessource::{BytesSource, transient_error};
+
+pub struct HttpSource {
+    client: Client,
+    url: Url,
+    len: u64,
+    // the resource's ETag, or failing that, its last modification time
+    version: Option<String>
+}
+
+impl HttpSource {
+    pub fn new(
+        client: Client,
+        url: Url,
+        len: u64,
+        version: Option<String>
+    ) -> Self
+    {
+        Self {
+            client,
+            url,
+            len,
+            version
+        }
+    }
+}
+
+// Returns an error for a failed response, marking server errors and
+// throttling as transient
+pub fn status_error<E>(status: StatusCode, e: E) -> std::io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>
+{
+    match status {
+        StatusCode::TOO_MANY_REQUESTS => transient_error(e),
+        s if s.is_server_error() => transient_error(e),
+        StatusCode::NOT_FOUND => std::io::Error::new(ErrorKind::NotFound, e),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN =>
+            std::io::Error::new(ErrorKind::PermissionDenied, e),
+        _ => std::io::Error::other(e)
+    }
+}
+
+// Returns an error for a failed request, marking those which failed in
+// connecting or in transit as transient
+pub fn request_error(e: reqwest::Error) -> std::io::Error {
+    match e.status() {
+        Some(s) => status_error(s, e),
+        None if e.is_timeout() => std::io::Error::new(ErrorKind::TimedOut, e),
+        None if e.is_connect() || e.is_request() || e.is_body() =>
+            transient_error(e),
+        None => std::io::Error::other(e)
+    }
+}
+
+// Gets the length and version of the resource at url from a HEAD request.
+// We read the length header ourselves, as the response to a HEAD request
+// has no body and so Response::content_length() is always 0.
+pub async fn head_length_version(
+    client: &Client,
+    url: Url
+) -> Result<(u64, Option<String>), std::io::Error>
+{
+    let resp = client.head(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(request_error)?;
+
+    let headers = resp.headers();
+
+    let len = headers.get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .ok_or(std::io::Error::other("missing or bad Content-Length"))?;
+
+    let version = headers.get(ETAG)
+        .or_else(|| headers.get(LAST_MODIFIED))
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+
+    Ok((len, version))
+}
+
+impl BytesSource for HttpSource {
+    fn read(
+        &self,
+        beg: u64,
+        end: u64
+    ) -> BoxFuture<'static, Result<Vec<u8>, std::io::Error>>
+    {
+        let req = self.client.get(self.url.clone())
+            // inclusive, augh!
+            .header(RANGE, format!("bytes={}-{}", beg, end.saturating_sub(1)));
+
+        async move {
+            if beg >= end {
+                return Ok(vec![]);
+            }
+
+            let resp = req.send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(request_error)?;
+
+            if resp.status() != StatusCode::PARTIAL_CONTENT {
+                return Err(std::io::Error::other(
+                    format!("expected a partial response, got {}", resp.status())
+                ));
+            }
+
+            let buf = resp.bytes()
+                .await
+                .map_err(request_error)?;
+
+            if buf.len() as u64 != end - beg {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!("expected {} bytes, got {}", end - beg, buf.len())
+                ));
+            }
+
+            trace!("read [{beg},{end}) from HTTP");
+            Ok(buf.to_vec())
+        }.boxed()
+    }
+
+    fn end(&self) -> u64 {
+        self.len
+    }
+
+    fn version(&self) -> Option<String> {
+        self.version.clone()
+    }
+}
@@ -0,0 +1,368 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{
+    cursor::{BUFFER_CHUNKS, E01Cursor, seek_position},
+    e01_reader::{E01Reader, E01ReaderOptions, OpenError},
+    ltree::{Record, parse_category}
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Permission {
+    pub name: Option<String>,
+    // e.g., a Windows SID
+    pub identifier: Option<String>,
+    pub access_mask: Option<u32>,
+    // all the values, including the ones above
+    pub values: Vec<(String, String)>
+}
+
+impl Permission {
+    fn from_record(rec: &Record) -> Self {
+        Permission {
+            name: nonempty(rec.get("n")),
+            identifier: nonempty(rec.get("s")),
+            access_mask: rec.get("nta").and_then(|v| v.trim().parse().ok()),
+            values: rec.values.clone()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct L01Entry {
+    pub name: String,
+    pub id: Option<u64>,
+    pub is_directory: bool,
+    pub size: u64,
+    // (offset, length) ranges of the image holding the file data, in order
+    pub extents: Vec<(u64, u64)>,
+    // seconds since the Unix epoch
+    pub creation_time: Option<i64>,
+    pub access_time: Option<i64>,
+    pub modification_time: Option<i64>,
+    pub entry_modification_time: Option<i64>,
+    pub deletion_time: Option<i64>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    // index into L01Reader::permission_groups()
+    pub permission_group: Option<usize>,
+    pub children: Vec<L01Entry>
+}
+
+fn nonempty(v: Option<&str>) -> Option<String> {
+    v.map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+}
+
+fn parse_time(v: Option<&str>) -> Option<i64> {
+    v.and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|t| *t != 0)
+}
+
+// Extents are given as a count followed by hex offset, length pairs.
+fn parse_extents(v: &str) -> Option<Vec<(u64, u64)>> {
+    let mut tokens = v.split_whitespace();
+    tokens.next()?;
+
+    let mut extents = vec![];
+    while let Some(off) = tokens.next() {
+        let len = tokens.next()?;
+        extents.push((
+            u64::from_str_radix(off, 16).ok()?,
+            u64::from_str_radix(len, 16).ok()?
+        ));
+    }
+
+    Some(extents)
+}
+
+impl L01Entry {
+    fn from_record(rec: &Record) -> Self {
+        let size = rec.get("ls")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0);
+
+        let extents = rec.get("be")
+            .and_then(parse_extents)
+            .filter(|e| !e.is_empty())
+            .or_else(||
+                // files duplicating another file give only its data offset
+                rec.get("du")
+                    .and_then(|v| u64::from_str_radix(v.trim(), 16).ok())
+                    .filter(|_| size > 0)
+                    .map(|off| vec![(off, size)])
+            )
+            .unwrap_or_default();
+
+        let children = rec.children.iter()
+            .map(Self::from_record)
+            .collect::<Vec<_>>();
+
+        L01Entry {
+            name: rec.get("n").unwrap_or_default().to_string(),
+            id: rec.get("id").and_then(|v| v.trim().parse().ok()),
+            is_directory: rec.get("p") == Some("1") || !children.is_empty(),
+            size,
+            extents,
+            creation_time: parse_time(rec.get("cr")),
+            access_time: parse_time(rec.get("ac")),
+            modification_time: parse_time(rec.get("wr")),
+            entry_modification_time: parse_time(rec.get("mo")),
+            deletion_time: parse_time(rec.get("dl")),
+            md5: nonempty(rec.get("ha")),
+            sha1: nonempty(rec.get("sha")),
+            permission_group: rec.get("pm")
+                .and_then(|v| v.trim().parse().ok()),
+            children
+        }
+    }
+
+    // Looks up a descendant by a '/'-separated path relative to this entry.
+    pub fn find(&self, path: &str) -> Option<&L01Entry> {
+        path.split('/')
+            .filter(|c| !c.is_empty())
+            .try_fold(self, |e, c| e.children.iter().find(|ch| ch.name == c))
+    }
+}
+
+pub struct L01Reader {
+    reader: E01Reader,
+    root: L01Entry,
+    permission_groups: Vec<Vec<Permission>>
+}
+
+impl L01Reader {
+    pub fn open_glob<T: AsRef<str>>(
+        example_segment_path: T,
+        options: &E01ReaderOptions
+    ) -> Result<Self, OpenError>
+    {
+        Self::from_reader(E01Reader::open_glob(example_segment_path, options)?)
+    }
+
+    pub fn open<T: IntoIterator<Item: AsRef<str>>>(
+        segment_paths: T,
+        options: &E01ReaderOptions
+    ) -> Result<Self, OpenError>
+    {
+        Self::from_reader(E01Reader::open(segment_paths, options)?)
+    }
+
+    pub fn from_reader(mut reader: E01Reader) -> Result<Self, OpenError> {
+        let text = reader.take_ltree()
+            .ok_or(OpenError::MissingLTreeSection)?;
+
+        let root = parse_category(&text, "entry")
+            .map_err(OpenError::BadLTreeSection)?
+            .ok_or(OpenError::MissingLTreeSection)?;
+
+        let permission_groups = parse_category(&text, "perm")
+            .map_err(OpenError::BadLTreeSection)?
+            .map(|rec| rec.children.iter()
+                .map(|g| g.children.iter()
+                    .map(Permission::from_record)
+                    .collect()
+                )
+                .collect()
+            )
+            .unwrap_or_default();
+
+        Ok(L01Reader {
+            reader,
+            root: L01Entry::from_record(&root),
+            permission_groups
+        })
+    }
+
+    pub fn root(&self) -> &L01Entry {
+        &self.root
+    }
+
+    pub fn permission_groups(&self) -> &[Vec<Permission>] {
+        &self.permission_groups
+    }
+
+    pub fn find(&self, path: &str) -> Option<&L01Entry> {
+        self.root.find(path)
+    }
+
+    pub fn open_file(&self, path: &str) -> Option<L01FileReader<'_>> {
+        let entry = self.root.find(path)?;
+        if entry.is_directory {
+            return None;
+        }
+
+        Some(L01FileReader::new(&self.reader, &entry.extents, entry.size))
+    }
+
+    pub fn into_inner(self) -> E01Reader {
+        self.reader
+    }
+}
+
+// A Read + Seek view of the data of a file in a logical evidence file.
+// Reads are buffered as by E01Cursor, with a buffer no larger than the file.
+pub struct L01FileReader<'a> {
+    cursor: E01Cursor<'a>,
+    extents: Vec<(u64, u64)>,
+    len: u64,
+    pos: u64
+}
+
+impl<'a> L01FileReader<'a> {
+    fn new(
+        reader: &'a E01Reader,
+        extents: &[(u64, u64)],
+        size: u64
+    ) -> Self
+    {
+        let len = extents.iter()
+            .map(|(_, l)| l)
+            .sum::<u64>()
+            .min(size);
+
+        let capacity = usize::try_from(len)
+            .unwrap_or(usize::MAX)
+            .min(reader.chunk_size * BUFFER_CHUNKS);
+
+        Self {
+            cursor: E01Cursor::with_capacity(reader, capacity),
+            extents: extents.to_vec(),
+            len,
+            pos: 0
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Read for L01FileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos);
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        // find the extent containing pos
+        let mut ext_beg = 0;
+        for &(off, ext_len) in &self.extents {
+            if self.pos < ext_beg + ext_len {
+                let in_ext = self.pos - ext_beg;
+                let len = buf.len()
+                    .min((ext_len - in_ext) as usize)
+                    .min(remaining as usize);
+
+                self.cursor.seek(SeekFrom::Start(off + in_ext))?;
+                let read = self.cursor.read(&mut buf[..len])?;
+
+                self.pos += read as u64;
+                return Ok(read);
+            }
+            ext_beg += ext_len;
+        }
+
+        Ok(0)
+    }
+}
+
+impl Seek for L01FileReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.pos = seek_position(pos, self.pos, self.len)?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rec(values: &[(&str, &str)], children: Vec<Record>) -> Record {
+        Record {
+            values: values.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            children
+        }
+    }
+
+    #[test]
+    fn parse_extents_ok() {
+        assert_eq!(
+            parse_extents("2 1a00 200 4000 10"),
+            Some(vec![(0x1a00, 0x200), (0x4000, 0x10)])
+        );
+        assert_eq!(parse_extents("1 1a00"), None);
+        assert_eq!(parse_extents(""), None);
+    }
+
+    #[test]
+    fn entry_from_record() {
+        let root = rec(
+            &[("p", "1"), ("n", "")],
+            vec![
+                rec(
+                    &[("p", "1"), ("n", "dir"), ("cr", "0")],
+                    vec![
+                        rec(
+                            &[
+                                ("p", "0"),
+                                ("n", "a.txt"),
+                                ("id", "3"),
+                                ("ls", "12"),
+                                ("be", "1 400 c"),
+                                ("cr", "1700000000"),
+                                ("ha", "0123456789abcdef0123456789abcdef"),
+                                ("sha", ""),
+                                ("pm", "1")
+                            ],
+                            vec![]
+                        ),
+                        rec(
+                            &[("p", "0"), ("n", "b.txt"), ("ls", "12"), ("du", "400")],
+                            vec![]
+                        )
+                    ]
+                )
+            ]
+        );
+
+        let root = L01Entry::from_record(&root);
+        assert!(root.is_directory);
+
+        let dir = root.find("dir").unwrap();
+        assert!(dir.is_directory);
+        assert_eq!(dir.creation_time, None);
+
+        let a = root.find("/dir/a.txt").unwrap();
+        assert!(!a.is_directory);
+        assert_eq!(a.id, Some(3));
+        assert_eq!(a.size, 12);
+        assert_eq!(a.extents, [(0x400, 12)]);
+        assert_eq!(a.creation_time, Some(1700000000));
+        assert_eq!(a.md5.as_deref(), Some("0123456789abcdef0123456789abcdef"));
+        assert_eq!(a.sha1, None);
+        assert_eq!(a.permission_group, Some(1));
+
+        let b = root.find("dir/b.txt").unwrap();
+        assert_eq!(b.extents, [(0x400, 12)]);
+
+        assert!(root.find("dir/c.txt").is_none());
+    }
+
+    #[test]
+    fn permission_from_record() {
+        let p = Permission::from_record(
+            &rec(&[("n", "Everyone"), ("s", "S-1-1-0"), ("nta", "2032127")], vec![])
+        );
+
+        assert_eq!(p.name.as_deref(), Some("Everyone"));
+        assert_eq!(p.identifier.as_deref(), Some("S-1-1-0"));
+        assert_eq!(p.access_mask, Some(2032127));
+    }
+}
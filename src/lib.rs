@@ -9,31 +9,60 @@ mod test_data;
 #[cfg(test)]
 mod test_helper;
 
+mod blockfetch;
+mod blocking;
+mod bufworkersource;
+pub mod bytessource;
 mod cache;
 mod cachereadseek;
 mod cacheworkersource;
-mod bytessource;
+pub mod case_metadata;
+mod chunkcache;
+pub mod chunkstream;
+pub mod cursor;
 mod dummycache;
 mod error;
+mod filepool;
 mod filesource;
 mod foyercache;
 mod generated;
 pub mod hasher;
+mod httpsource;
+pub mod l01_reader;
+mod ltree;
+mod mapfile;
+pub mod media_info;
 mod placeholdersource;
 mod readworker;
+mod retrysource;
 mod s3source;
 mod sec_read;
+mod sec_read_v2;
 mod seg_path;
 mod segment;
+pub mod session;
 mod workersource;
+mod xmlsection;
 
 #[cfg(test)]
 mod test {
+    use futures::future::{BoxFuture, FutureExt};
+    use md5::{Digest, Md5};
+    use std::{
+        io::{Read, Seek, SeekFrom},
+        sync::Arc
+    };
+
     use crate::{
-        e01_reader::{CorruptChunkPolicy, CorruptSectionPolicy, E01Reader, E01ReaderOptions},
+        bytessource::BytesSource,
+        case_metadata::{CaseMetadata, HeaderDate},
+        e01_reader::{CacheOptions, ChunkCacheStats, CorruptChunkPolicy, CorruptSectionPolicy, E01Reader, E01ReaderOptions, InitError, OpenError, TableCopy},
         hasher::HashType,
+        media_info::{CompressionLevel, MediaFlags, MediaInfo, MediaType},
+        segment::CompressionMethod,
+        session::SectorRangeReader,
         test_data::*,
-        test_helper::do_hash
+        test_helper::{Ewf2Chunk, do_hash, ewf2_segment, serve_http}
     };
 
     #[track_caller]
@@ -128,12 +157,24 @@ mod test {
 
     const ERROR_ERROR: E01ReaderOptions = E01ReaderOptions {
         corrupt_section_policy: CorruptSectionPolicy::Error,
-        corrupt_chunk_policy: CorruptChunkPolicy::Error
+        corrupt_chunk_policy: CorruptChunkPolicy::Error,
+        runtime: None,
+        s3: None,
+        retry: None,
+        cache: None,
+        read_parallelism: None,
+        max_open_files: None
     };
 
     const ERROR_ZERO: E01ReaderOptions = E01ReaderOptions {
         corrupt_section_policy: CorruptSectionPolicy::Error,
-        corrupt_chunk_policy: CorruptChunkPolicy::Zero
+        corrupt_chunk_policy: CorruptChunkPolicy::Zero,
+        runtime: None,
+        s3: None,
+        retry: None,
+        cache: None,
+        read_parallelism: None,
+        max_open_files: None
     };
 
     #[test]
@@ -169,6 +210,754 @@ mod test {
         assert_eq_test_data(&BAD_CHUNK_E01_ZEROED, &ERROR_ZERO);
     }
 
+    #[test]
+    fn test_image_e01_case_metadata() {
+        let reader = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        // image.E01 has both header and header2; header2 wins
+        let exp = CaseMetadata {
+            acquisition_software_version: Some("20201230".into()),
+            acquisition_os: Some("Linux".into()),
+            acquisition_date: Some(HeaderDate::Unix(1747751770)),
+            system_date: Some(HeaderDate::Unix(1747751770)),
+            ..Default::default()
+        };
+
+        assert_eq!(reader.case_metadata, Some(exp));
+    }
+
+    #[test]
+    fn test_mimage_e01_case_metadata() {
+        let reader = E01Reader::open_glob(
+            MIMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        let exp = CaseMetadata {
+            description: Some("untitled".into()),
+            acquisition_software_version: Some("ADI4.7.1.2".into()),
+            acquisition_os: Some("Win 201x".into()),
+            acquisition_date: Some(HeaderDate::Local(2023, 6, 20, 10, 45, 24)),
+            system_date: Some(HeaderDate::Local(2023, 6, 20, 10, 45, 24)),
+            compression_level: Some("f".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(reader.case_metadata, Some(exp));
+    }
+
+    fn image_e01_corrupt_header2() -> (tempfile::TempDir, String) {
+        let mut data = std::fs::read(IMAGE_E01.segment_paths[0]).unwrap();
+
+        // break the zlib header of each header2 section, which follows the
+        // 76-byte section descriptor
+        let sections = data.windows(8)
+            .enumerate()
+            .filter(|(_, w)| *w == b"header2\0")
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        assert_eq!(sections.len(), 2);
+
+        for s in sections {
+            data[s + 76] = 0xff;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.E01");
+        std::fs::write(&path, data).unwrap();
+        let path = path.to_str().unwrap().to_string();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_image_e01_corrupt_header2_error() {
+        let (_dir, path) = image_e01_corrupt_header2();
+
+        assert!(E01Reader::open_glob(&path, &ERROR_ERROR).is_err());
+    }
+
+    #[test]
+    fn test_image_e01_corrupt_header2_skipped() {
+        let (_dir, path) = image_e01_corrupt_header2();
+
+        let mut reader = E01Reader::open_glob(
+            &path,
+            &E01ReaderOptions {
+                corrupt_section_policy: CorruptSectionPolicy::DamnTheTorpedoes,
+                ..ERROR_ERROR
+            }
+        ).unwrap();
+
+        // the header section is used instead, which has local dates
+        let exp = CaseMetadata {
+            acquisition_software_version: Some("20201230".into()),
+            acquisition_os: Some("Linux".into()),
+            acquisition_date: Some(HeaderDate::Local(2025, 5, 20, 15, 36, 10)),
+            system_date: Some(HeaderDate::Local(2025, 5, 20, 15, 36, 10)),
+            ..Default::default()
+        };
+
+        assert_eq!(reader.case_metadata, Some(exp));
+
+        let image_size = reader.image_size;
+
+        let hashes = do_hash(
+            |offset, buf: &mut [u8]| {
+                let buf_len = buf.len();
+                reader.read_at_offset(offset, &mut buf[..buf_len])
+                    .unwrap()
+            },
+            image_size,
+            false
+        );
+
+        assert_eq!(hashes.get(&HashType::MD5).map(String::as_str), IMAGE_E01.md5);
+    }
+
+    #[test]
+    fn test_image_e01_media_info() {
+        let reader = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        let exp = MediaInfo {
+            media_type: Some(MediaType::Fixed),
+            media_flags: MediaFlags(0x03),
+            compression_level: CompressionLevel::None,
+            error_granularity: 64,
+            set_identifier: [
+                0x64, 0x7d, 0x7b, 0xf3, 0x8e, 0x1c, 0x79, 0x4f,
+                0xa2, 0xba, 0xdf, 0x18, 0x6b, 0xd9, 0x58, 0xfa
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(reader.media_info, Some(exp));
+
+        let flags = reader.media_info.unwrap().media_flags;
+        assert!(flags.is_physical());
+        assert!(!flags.is_write_blocked());
+    }
+
+    #[test]
+    fn test_image_e01_no_acquisition_errors() {
+        let reader = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        assert!(reader.acquisition_errors().is_empty());
+    }
+
+    #[test]
+    fn test_image_e01_no_sessions() {
+        let reader = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        assert!(reader.sessions().is_empty());
+        assert!(reader.session_reader(0).is_none());
+    }
+
+    #[test]
+    fn test_image_e01_sector_range_reader() {
+        let reader = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        let mut exp = vec![0; 9 * reader.sector_size];
+        reader.read_at(reader.sector_size as u64, &mut exp).unwrap();
+
+        // small reads, served from the buffer
+        let mut range = SectorRangeReader::new(&reader, 1, 9).unwrap();
+        let mut act = vec![0; exp.len()];
+        for piece in act.chunks_mut(100) {
+            range.read_exact(piece).unwrap();
+        }
+        assert_eq!(act, exp);
+        assert_eq!(range.read(&mut [0; 1]).unwrap(), 0);
+
+        range.seek(SeekFrom::Start(1000)).unwrap();
+        let mut buf = [0; 10];
+        range.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, exp[1000..1010]);
+    }
+
+    #[test]
+    fn test_image_e01_sector_range_reader_overflow() {
+        let reader = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        assert!(SectorRangeReader::new(&reader, u64::MAX, 1).is_err());
+        assert!(SectorRangeReader::new(&reader, u64::MAX / 2, 0).is_err());
+        assert!(SectorRangeReader::new(&reader, 1 << 40, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_image_e01_corrupt_table_uses_table2() {
+        let mut data = std::fs::read(IMAGE_E01.segment_paths[0]).unwrap();
+
+        // flip a bit in the first entry of the table section, which
+        // follows the 76-byte section descriptor and 24-byte table header
+        let table = data.windows(6)
+            .position(|w| w == b"table\0")
+            .unwrap();
+        data[table + 76 + 24] ^= 0x01;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.E01");
+        std::fs::write(&path, data).unwrap();
+
+        let mut reader = E01Reader::open_glob(
+            path.to_str().unwrap(),
+            &ERROR_ERROR
+        ).unwrap();
+
+        assert_eq!(reader.table_copies(), [TableCopy::Table2]);
+
+        let image_size = reader.image_size;
+
+        let hashes = do_hash(
+            |offset, buf: &mut [u8]| {
+                let buf_len = buf.len();
+                reader.read_at_offset(offset, &mut buf[..buf_len])
+                    .unwrap()
+            },
+            image_size,
+            false
+        );
+
+        assert_eq!(hashes.get(&HashType::MD5).map(String::as_str), IMAGE_E01.md5);
+    }
+
+    // An Ex01 image of four 4 KiB chunks, the last one short, stored in each
+    // of the ways EWF2 allows
+    fn ex01_image(
+        compr_method: CompressionMethod,
+        errors: &[(u64, u32)]
+    ) -> (tempfile::TempDir, String, Vec<u8>)
+    {
+        let mut data = (0..14336_u32)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect::<Vec<_>>();
+
+        // the third chunk repeats an 8-byte pattern
+        data[8192..12288].iter_mut()
+            .enumerate()
+            .for_each(|(i, b)| *b = (i % 8) as u8 + 1);
+
+        let seg = ewf2_segment(
+            &data,
+            8,
+            compr_method,
+            &[
+                Ewf2Chunk::Compressed,
+                Ewf2Chunk::Checksummed,
+                Ewf2Chunk::Pattern,
+                Ewf2Chunk::Compressed
+            ],
+            errors
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.Ex01");
+        std::fs::write(&path, seg).unwrap();
+        let path = path.to_str().unwrap().to_string();
+        (dir, path, data)
+    }
+
+    #[track_caller]
+    fn assert_ex01_md5(path: &str, data: &[u8], options: &E01ReaderOptions) {
+        let reader = E01Reader::open_glob(path, options).unwrap();
+
+        let md5 = Md5::digest(data);
+        assert_eq!(reader.image_size, data.len() as u64);
+        assert_eq!(reader.stored_md5, Some(md5.into()));
+
+        let hashes = do_hash(
+            |offset, buf: &mut [u8]| reader.read_at(offset, buf).unwrap(),
+            reader.image_size,
+            true
+        );
+
+        assert_eq!(hashes.get(&HashType::MD5), Some(&hex::encode(md5)));
+    }
+
+    #[test]
+    fn test_ex01_deflate() {
+        let (_dir, path, data) = ex01_image(CompressionMethod::Deflate, &[]);
+        assert_ex01_md5(&path, &data, &ERROR_ERROR);
+    }
+
+    #[test]
+    fn test_ex01_bzip() {
+        let (_dir, path, data) = ex01_image(CompressionMethod::Bzip, &[]);
+        assert_ex01_md5(&path, &data, &ERROR_ERROR);
+    }
+
+    #[test]
+    fn test_ex01_bad_descriptor_checksum() {
+        let (_dir, path, data) = ex01_image(CompressionMethod::Deflate, &[]);
+
+        // flip a bit in the padding of the done section's descriptor, at
+        // the end of the segment
+        let mut seg = std::fs::read(&path).unwrap();
+        let len = seg.len();
+        seg[len - 8] ^= 0x01;
+        std::fs::write(&path, seg).unwrap();
+
+        assert!(E01Reader::open_glob(&path, &ERROR_ERROR).is_err());
+
+        assert_ex01_md5(
+            &path,
+            &data,
+            &E01ReaderOptions {
+                corrupt_section_policy: CorruptSectionPolicy::DamnTheTorpedoes,
+                ..ERROR_ERROR
+            }
+        );
+    }
+
+    #[test]
+    fn test_ex01_acquisition_errors() {
+        let (_dir, path, data) = ex01_image(
+            CompressionMethod::Deflate,
+            &[(2, 3), (20, 4)]
+        );
+
+        let reader = E01Reader::open_glob(&path, &ERROR_ERROR).unwrap();
+        assert_eq!(reader.acquisition_errors(), [(2, 3), (20, 4)]);
+
+        let mut mapfile = vec![];
+        reader.write_ddrescue_mapfile(&mut mapfile).unwrap();
+
+        // the errors are bad, and the rest finished
+        let text = String::from_utf8(mapfile).unwrap();
+        assert_eq!(
+            text.lines().skip(4).collect::<Vec<_>>(),
+            [
+                "0x00000000  0x00000400  +",
+                "0x00000400  0x00000600  -",
+                "0x00000A00  0x00001E00  +",
+                "0x00002800  0x00000800  -",
+                "0x00003000  0x00000800  +"
+            ]
+        );
+
+        // the errors don't affect the image data
+        assert_ex01_md5(&path, &data, &ERROR_ERROR);
+    }
+
+    #[test]
+    fn test_mimage_e01_cursor_copy() {
+        let reader = E01Reader::open_glob(
+            MIMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        let mut hasher = Md5::new();
+        let copied = std::io::copy(&mut reader.cursor(), &mut hasher).unwrap();
+
+        assert_eq!(copied, MIMAGE_E01.image_size);
+        assert_eq!(Some(hex::encode(hasher.finalize()).as_str()), MIMAGE_E01.md5);
+    }
+
+    #[test]
+    fn test_image_e01_cursor_seek() {
+        let mut reader = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        let mut exp = vec![0; 1000];
+        reader.read_at_offset(IMAGE_E01.image_size - 1000, &mut exp).unwrap();
+
+        let mut cursor = reader.cursor();
+
+        assert_eq!(cursor.seek(SeekFrom::End(-1000)).unwrap(), IMAGE_E01.image_size - 1000);
+        let mut act = vec![];
+        cursor.read_to_end(&mut act).unwrap();
+        assert_eq!(act, exp);
+
+        // reads at and past the end return nothing
+        assert_eq!(cursor.read(&mut [0; 10]).unwrap(), 0);
+        cursor.seek(SeekFrom::Current(10)).unwrap();
+        assert_eq!(cursor.read(&mut [0; 10]).unwrap(), 0);
+
+        assert!(cursor.seek(SeekFrom::Current(-(IMAGE_E01.image_size as i64) - 11)).is_err());
+    }
+
+    #[test]
+    fn test_image_e01_concurrent_read_at() {
+        let reader = Arc::new(
+            E01Reader::open_glob(IMAGE_E01.segment_paths[0], &ERROR_ERROR)
+                .unwrap()
+        );
+
+        let threads = (0..4)
+            .map(|_| {
+                let reader = reader.clone();
+                std::thread::spawn(move || {
+                    let image_size = reader.image_size;
+                    do_hash(
+                        |offset, buf: &mut [u8]| {
+                            let buf_len = buf.len();
+                            reader.read_at(offset, &mut buf[..buf_len])
+                                .unwrap()
+                        },
+                        image_size,
+                        false
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for t in threads {
+            let hashes = t.join().unwrap();
+            assert_eq!(hashes.get(&HashType::MD5).map(String::as_str), IMAGE_E01.md5);
+        }
+    }
+
+    #[test]
+    fn test_mimage_e01_stream_chunks() {
+        let reader = Arc::new(
+            E01Reader::open_glob(MIMAGE_E01.segment_paths[0], &ERROR_ERROR)
+                .unwrap()
+        );
+
+        let mut hasher = Md5::new();
+        let mut len = 0;
+        for ch in reader.stream_chunks(0..reader.image_size) {
+            let ch = ch.unwrap();
+            assert!(ch.len() <= reader.chunk_size);
+            len += ch.len() as u64;
+            hasher.update(&ch);
+        }
+
+        assert_eq!(len, MIMAGE_E01.image_size);
+        assert_eq!(
+            Some(hex::encode(hasher.finalize()).as_str()),
+            MIMAGE_E01.md5
+        );
+    }
+
+    #[test]
+    fn test_image_e01_stream_chunks_range() {
+        let reader = Arc::new(
+            E01Reader::open_glob(IMAGE_E01.segment_paths[0], &ERROR_ERROR)
+                .unwrap()
+        );
+
+        let beg = reader.chunk_size as u64 * 3 - 100;
+        let end = reader.chunk_size as u64 * 5 + 100;
+
+        let mut exp = vec![0; (end - beg) as usize];
+        reader.read_at(beg, &mut exp).unwrap();
+
+        let chunks = reader.stream_chunks(beg..end)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+            [100, reader.chunk_size, reader.chunk_size, 100]
+        );
+        assert_eq!(chunks.concat(), exp);
+
+        // ranges are clipped to the image
+        let size = reader.image_size;
+        assert_eq!(reader.stream_chunks(size..size + 10).count(), 0);
+    }
+
+    #[test]
+    fn test_mimage_e01_one_open_file() {
+        let reader = E01Reader::open_glob(
+            MIMAGE_E01.segment_paths[0],
+            &E01ReaderOptions {
+                max_open_files: Some(1),
+                ..ERROR_ERROR
+            }
+        ).unwrap();
+
+        // reads span both segments, so each read reopens them
+        let hashes = do_hash(
+            |offset, buf: &mut [u8]| reader.read_at(offset, buf).unwrap(),
+            reader.image_size,
+            false
+        );
+
+        assert_eq!(hashes.get(&HashType::MD5).map(String::as_str), MIMAGE_E01.md5);
+    }
+
+    #[test]
+    fn test_image_e01_chunk_cache() {
+        let reader = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &E01ReaderOptions {
+                cache: Some(CacheOptions {
+                    chunk_cache_size: 1024 * 1024,
+                    ..Default::default()
+                }),
+                ..ERROR_ERROR
+            }
+        ).unwrap();
+
+        // none are cached by default
+        let uncached = E01Reader::open_glob(
+            IMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        let mut exp = vec![0; 512];
+        uncached.read_at(1000, &mut exp).unwrap();
+        assert_eq!(uncached.chunk_cache_stats(), ChunkCacheStats::default());
+
+        // the first read decompresses the chunk, the rest hit the cache
+        for _ in 0..3 {
+            let mut act = vec![0; 512];
+            reader.read_at(1000, &mut act).unwrap();
+            assert_eq!(act, exp);
+        }
+
+        let stats = reader.chunk_cache_stats();
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+        assert_eq!(stats.size, reader.chunk_size);
+    }
+
+    #[test]
+    fn test_mimage_e01_async_read_at() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let reader = E01Reader::open_glob_async(
+                MIMAGE_E01.segment_paths[0],
+                &ERROR_ERROR
+            ).await.unwrap();
+
+            let mut hasher = Md5::new();
+            let mut buf = vec![0; 100_000];
+            let mut offset = 0;
+
+            while offset < reader.image_size {
+                let read = reader.read_at_async(offset, &mut buf).await.unwrap();
+                hasher.update(&buf[..read]);
+                offset += read as u64;
+            }
+
+            assert_eq!(Some(hex::encode(hasher.finalize()).as_str()), MIMAGE_E01.md5);
+        });
+    }
+
+    #[test]
+    fn test_mimage_e01_blocking_reads_in_async() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let reader = Arc::new(
+                E01Reader::open_glob_async(
+                    MIMAGE_E01.segment_paths[0],
+                    &ERROR_ERROR
+                ).await.unwrap()
+            );
+
+            let mut buf = vec![0; 1000];
+            assert_eq!(reader.read_at(5000, &mut buf).unwrap(), buf.len());
+
+            let mut hasher = Md5::new();
+            std::io::copy(&mut reader.cursor(), &mut hasher).unwrap();
+            assert_eq!(Some(hex::encode(hasher.finalize()).as_str()), MIMAGE_E01.md5);
+
+            let mut hasher = Md5::new();
+            for ch in reader.stream_chunks(0..reader.image_size) {
+                hasher.update(ch.unwrap());
+            }
+            assert_eq!(Some(hex::encode(hasher.finalize()).as_str()), MIMAGE_E01.md5);
+        });
+    }
+
+    #[test]
+    fn test_mimage_e01_blocking_reads_in_current_thread_async() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let caller = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let options = E01ReaderOptions {
+            runtime: Some(runtime.handle().clone()),
+            ..ERROR_ERROR
+        };
+
+        caller.block_on(async {
+            let reader = E01Reader::open_glob_async(
+                MIMAGE_E01.segment_paths[0],
+                &options
+            ).await.unwrap();
+
+            let mut hasher = Md5::new();
+            std::io::copy(&mut reader.cursor(), &mut hasher).unwrap();
+            assert_eq!(Some(hex::encode(hasher.finalize()).as_str()), MIMAGE_E01.md5);
+        });
+    }
+
+    #[test]
+    fn test_current_thread_runtime() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        assert!(matches!(
+            runtime.block_on(E01Reader::open_glob_async(
+                IMAGE_E01.segment_paths[0],
+                &ERROR_ERROR
+            )),
+            Err(OpenError::InitializationFailed(InitError::CurrentThreadRuntime))
+        ));
+    }
+
+    #[test]
+    fn test_shared_runtime() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let options = E01ReaderOptions {
+            runtime: Some(runtime.handle().clone()),
+            ..ERROR_ERROR
+        };
+
+        for exp in [&IMAGE_E01, &MIMAGE_E01] {
+            let reader = E01Reader::open_glob(exp.segment_paths[0], &options)
+                .unwrap();
+
+            let mut hasher = Md5::new();
+            std::io::copy(&mut reader.cursor(), &mut hasher).unwrap();
+
+            assert_eq!(Some(hex::encode(hasher.finalize()).as_str()), exp.md5);
+        }
+    }
+
+    struct VecSource(Arc<Vec<u8>>);
+
+    impl BytesSource for VecSource {
+        fn read(
+            &self,
+            beg: u64,
+            end: u64
+        ) -> BoxFuture<'static, Result<Vec<u8>, std::io::Error>>
+        {
+            let data = self.0.clone();
+            async move { Ok(data[beg as usize..end as usize].to_vec()) }.boxed()
+        }
+
+        fn end(&self) -> u64 {
+            self.0.len() as u64
+        }
+    }
+
+    #[test]
+    fn test_mimage_e01_open_sources() {
+        let sources = MIMAGE_E01.segment_paths.iter()
+            .map(|p| Box::new(VecSource(Arc::new(std::fs::read(p).unwrap())))
+                as Box<dyn BytesSource + Send>)
+            .collect();
+
+        let reader = E01Reader::open_sources(sources, &ERROR_ERROR).unwrap();
+
+        let mut hasher = Md5::new();
+        std::io::copy(&mut reader.cursor(), &mut hasher).unwrap();
+
+        assert_eq!(Some(hex::encode(hasher.finalize()).as_str()), MIMAGE_E01.md5);
+        assert_eq!(reader.segment_paths[1].to_str(), Some("<source 1>"));
+    }
+
+    #[test]
+    fn test_open_sources_none() {
+        assert!(matches!(
+            E01Reader::open_sources(vec![], &ERROR_ERROR),
+            Err(OpenError::NoSegmentFiles)
+        ));
+    }
+
+    #[test]
+    fn test_mimage_e01_http() {
+        let port = serve_http("data".into());
+
+        let reader = E01Reader::open_glob(
+            format!("http://127.0.0.1:{port}/mimage.E01"),
+            &ERROR_ERROR
+        ).unwrap();
+
+        assert_eq!(reader.segment_paths.len(), 2);
+
+        let mut hasher = Md5::new();
+        std::io::copy(&mut reader.cursor(), &mut hasher).unwrap();
+
+        assert_eq!(Some(hex::encode(hasher.finalize()).as_str()), MIMAGE_E01.md5);
+    }
+
+    #[test]
+    fn test_mimage_e01_http_query() {
+        let port = serve_http("data".into());
+
+        let reader = E01Reader::open_glob(
+            format!("http://127.0.0.1:{port}/mimage.E01?token=abc"),
+            &ERROR_ERROR
+        ).unwrap();
+
+        // the query is kept for every segment
+        assert_eq!(
+            reader.segment_paths,
+            [
+                format!("http://127.0.0.1:{port}/mimage.E01?token=abc"),
+                format!("http://127.0.0.1:{port}/mimage.E02?token=abc")
+            ]
+            .map(std::path::PathBuf::from)
+        );
+
+        let mut hasher = Md5::new();
+        std::io::copy(&mut reader.cursor(), &mut hasher).unwrap();
+
+        assert_eq!(Some(hex::encode(hasher.finalize()).as_str()), MIMAGE_E01.md5);
+    }
+
+    #[test]
+    fn test_mimage_e01_http_check_failed() {
+        let port = serve_http("data".into());
+
+        // a failed check for a segment isn't taken to mean it is missing
+        let e = E01Reader::open_glob(
+            format!("http://127.0.0.1:{port}/mimage.E01?fail=mimage.E02"),
+            &ERROR_ERROR
+        ).unwrap_err();
+
+        assert!(
+            matches!(&e, OpenError::IoError { path, .. } if path.contains("/mimage.E02")),
+            "{e:?}"
+        );
+    }
+
+    #[test]
+    fn test_mimage_e01_media_info() {
+        let reader = E01Reader::open_glob(
+            MIMAGE_E01.segment_paths[0],
+            &ERROR_ERROR
+        ).unwrap();
+
+        let exp = MediaInfo {
+            media_type: Some(MediaType::Fixed),
+            media_flags: MediaFlags(0x01),
+            ..Default::default()
+        };
+
+        assert_eq!(reader.media_info, Some(exp));
+    }
+
 /*
     #[test]
     fn test_imageformat_mmls_1_e01() {
@@ -0,0 +1,152 @@
+// ltree sections, and the single files data sections which replace them in
+// EWF2, hold several categories, each of which is a tree of records, e.g.,
+//
+// entry
+// 1	1
+// p	n	id	ls	...
+// 0	1
+// 1		1	0	...
+// 0	0
+// 0	a.txt	2	12	...
+//
+// The first line of a category is its name, which is followed by a line we
+// don't need, then the keys for the records. Each record is a line giving
+// its number of children, then a line of values; its children follow it.
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub values: Vec<(String, String)>,
+    pub children: Vec<Record>
+}
+
+impl Record {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn parse_records<'a, I: Iterator<Item = &'a str>>(
+    lines: &mut I,
+    keys: &[&str]
+) -> Result<Record, String>
+{
+    // records which are waiting for children, with the number remaining
+    let mut stack: Vec<(Record, usize)> = vec![];
+
+    loop {
+        let child_count = lines.next()
+            .filter(|l| !l.is_empty())
+            .ok_or("missing record header")?
+            .split('\t')
+            .nth(1)
+            .and_then(|n| n.trim().parse::<usize>().ok())
+            .ok_or("bad record header")?;
+
+        let values = lines.next()
+            .ok_or("missing record values")?
+            .split('\t');
+
+        let rec = Record {
+            values: keys.iter()
+                .zip(values)
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            children: vec![]
+        };
+
+        if child_count > 0 {
+            stack.push((rec, child_count));
+            continue;
+        }
+
+        // attach the finished record to its parent, and any parents which
+        // are thereby finished to theirs
+        let mut rec = rec;
+        loop {
+            match stack.last_mut() {
+                None => return Ok(rec),
+                Some((parent, remaining)) => {
+                    parent.children.push(rec);
+                    *remaining -= 1;
+                    if *remaining > 0 {
+                        break;
+                    }
+                    rec = stack.pop().expect("stack is not empty").0;
+                }
+            }
+        }
+    }
+}
+
+// Returns the root record of the named category, if present.
+pub fn parse_category(
+    text: &str,
+    name: &str
+) -> Result<Option<Record>, String>
+{
+    let mut lines = text.split('\n')
+        .map(|l| l.strip_suffix('\r').unwrap_or(l));
+
+    if lines.by_ref().find(|l| *l == name).is_none() {
+        return Ok(None);
+    }
+
+    let _counts = lines.next()
+        .ok_or_else(|| format!("missing {name} counts"))?;
+
+    let keys = lines.next()
+        .ok_or_else(|| format!("missing {name} keys"))?
+        .split('\t')
+        .collect::<Vec<_>>();
+
+    parse_records(&mut lines, &keys)
+        .map(Some)
+        .map_err(|e| format!("{name}: {e}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEXT: &str = "5\r\nrec\r\ntb\tcl\tn\r\n1b8\t0\t\r\n\r\nentry\r\n1\t1\r\np\tn\tls\r\n0\t2\r\n1\t\t0\r\n0\t1\r\n1\tdir\t0\r\n0\t0\r\n0\tb.txt\t5\r\n0\t0\r\n0\ta.txt\t12\r\n\r\n";
+
+    fn rec(values: &[(&str, &str)], children: Vec<Record>) -> Record {
+        Record {
+            values: values.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            children
+        }
+    }
+
+    #[test]
+    fn parse_category_ok() {
+        let exp = rec(
+            &[("p", "1"), ("n", ""), ("ls", "0")],
+            vec![
+                rec(
+                    &[("p", "1"), ("n", "dir"), ("ls", "0")],
+                    vec![rec(&[("p", "0"), ("n", "b.txt"), ("ls", "5")], vec![])]
+                ),
+                rec(&[("p", "0"), ("n", "a.txt"), ("ls", "12")], vec![])
+            ]
+        );
+
+        assert_eq!(parse_category(TEXT, "entry").unwrap(), Some(exp));
+    }
+
+    #[test]
+    fn parse_category_missing() {
+        assert_eq!(parse_category(TEXT, "perm").unwrap(), None);
+    }
+
+    #[test]
+    fn parse_category_truncated() {
+        assert_eq!(
+            parse_category("entry\n1\t1\np\tn\n0\t1\n1\t\n", "entry").unwrap_err(),
+            "entry: missing record header"
+        );
+    }
+}
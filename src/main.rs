@@ -2,9 +2,13 @@ use bytesize::ByteSize;
 use clap::Parser;
 use std::{
     collections::HashSet,
+    fs::File,
+    io::{BufWriter, Write},
     iter::FromIterator,
     ops::BitAndAssign,
+    path::PathBuf,
     process::ExitCode,
+    sync::Arc,
     time::{Duration, Instant}
 };
 use tracing_subscriber::{
@@ -14,7 +18,7 @@ use tracing_subscriber::{
 };
 
 use e01::{
-    e01_reader::{CorruptChunkPolicy, CorruptSectionPolicy, E01Error, E01Reader, E01ReaderOptions},
+    e01_reader::{CacheOptions, CorruptChunkPolicy, CorruptSectionPolicy, E01Error, E01Reader, E01ReaderOptions, RetryOptions, S3Credentials, S3Options, TableCopy},
     hasher::{HashType, MultiHasher}
 };
 
@@ -30,7 +34,55 @@ struct Args {
 
     /// Ignore all checksums during read, default value is false
     #[arg(short, long, default_value = "false")]
-    ignore_checksums: bool
+    ignore_checksums: bool,
+
+    /// Write acquisition errors to a ddrescue mapfile
+    #[arg(short, long)]
+    mapfile: Option<PathBuf>,
+
+    /// S3 region, e.g., eu-west-1
+    #[arg(long)]
+    s3_region: Option<String>,
+
+    /// S3 endpoint, for S3-compatible stores such as MinIO
+    #[arg(long)]
+    s3_endpoint: Option<String>,
+
+    /// Use path-style S3 addressing
+    #[arg(long, default_value = "false")]
+    s3_path_style: bool,
+
+    /// Read S3 credentials from the AWS_* environment variables
+    #[arg(long, default_value = "false")]
+    s3_env_credentials: bool,
+
+    /// Number of times to retry a failed read from S3 or HTTP(S)
+    #[arg(long, default_value = "5")]
+    max_retries: u32,
+
+    /// Timeout for each read from S3 or HTTP(S), in seconds
+    #[arg(long, default_value = "60")]
+    request_timeout: u64,
+
+    /// Directory for a disk cache of segment data, reused across runs
+    #[arg(long, requires = "cache_disk_size")]
+    cache_dir: Option<PathBuf>,
+
+    /// Size of the disk cache of segment data, in bytes; none if not given
+    #[arg(long)]
+    cache_disk_size: Option<usize>,
+
+    /// Number of cache blocks to read ahead
+    #[arg(long, default_value = "0")]
+    readahead: usize,
+
+    /// Maximum number of chunks to decompress at once
+    #[arg(long)]
+    read_parallelism: Option<usize>,
+
+    /// Maximum number of segment files to hold open at once
+    #[arg(long)]
+    max_open_files: Option<usize>
 }
 
 fn check_hash<H1: AsRef<[u8]>, H2: AsRef<[u8]>>(
@@ -76,7 +128,7 @@ fn display_progress(
 }
 
 fn run(args: Args)-> Result<ExitCode, E01Error> {
-    let mut e01_reader = E01Reader::open_glob(
+    let e01_reader = E01Reader::open_glob(
         &args.input,
         &E01ReaderOptions {
             corrupt_section_policy: CorruptSectionPolicy::Error,
@@ -85,10 +137,73 @@ fn run(args: Args)-> Result<ExitCode, E01Error> {
             }
             else {
                 CorruptChunkPolicy::Error
-            }
+            },
+            runtime: None,
+            s3: Some(S3Options {
+                region: args.s3_region.clone(),
+                endpoint: args.s3_endpoint.clone(),
+                path_style: args.s3_path_style,
+                credentials: if args.s3_env_credentials {
+                    S3Credentials::Environment
+                }
+                else {
+                    S3Credentials::Anonymous
+                }
+            }),
+            retry: Some(RetryOptions {
+                max_retries: args.max_retries,
+                request_timeout: Some(Duration::from_secs(args.request_timeout)),
+                ..Default::default()
+            }),
+            cache: Some(CacheOptions {
+                disk_size: args.cache_disk_size.unwrap_or(0),
+                readahead: args.readahead,
+                dir: args.cache_dir.clone(),
+                ..Default::default()
+            }),
+            read_parallelism: args.read_parallelism,
+            max_open_files: args.max_open_files
         }
     )?;
 
+    // report sectors which were unreadable at acquisition
+    let acquisition_errors = e01_reader.acquisition_errors();
+    if !acquisition_errors.is_empty() {
+        println!("Acquisition errors:");
+        for (first, count) in acquisition_errors {
+            println!(
+                "  sectors {}-{} ({} sectors)",
+                first,
+                first + count.saturating_sub(1),
+                count
+            );
+        }
+    }
+
+    // report chunk tables recovered from their backup copies
+    let recovered = e01_reader.table_copies()
+        .iter()
+        .filter(|&&c| c == TableCopy::Table2)
+        .count();
+
+    if recovered > 0 {
+        println!("Chunk tables read from table2 backups: {}", recovered);
+    }
+
+    if let Some(path) = &args.mapfile {
+        let r = File::create(path)
+            .and_then(|f| {
+                let mut w = BufWriter::new(f);
+                e01_reader.write_ddrescue_mapfile(&mut w)?;
+                w.flush()
+            });
+
+        if let Err(e) = r {
+            eprintln!("Failed to write mapfile {}: {}", path.display(), e);
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+
     let mut htypes: HashSet<HashType> = HashSet::from_iter(args.extra_hashes);
 
     // compute MD5 if we have one stored
@@ -101,22 +216,45 @@ fn run(args: Args)-> Result<ExitCode, E01Error> {
         htypes.insert(HashType::SHA1);
     }
 
-    let hasher = MultiHasher::new(htypes, vec![0; 1024 * 1024]);
+    // compute SHA256 if we have one stored in an xhash section; one which
+    // isn't hex can't be verified
+    let stored_sha256 = e01_reader.stored_hashes
+        .iter()
+        .find(|(name, _)| name == "sha256")
+        .map(|(_, value)| hex::decode(value).map_err(|_| value.clone()));
+
+    if stored_sha256.is_some() {
+        htypes.insert(HashType::SHA256);
+    }
+
+    let hasher = MultiHasher::new(htypes, Vec::new());
+
+    // stream the image through the hashers, which hash each batch of chunks
+    // while later chunks are decompressed; chunks are batched as each
+    // update waits for the hashers to finish the previous one
+    const HASH_BATCH_SIZE: usize = 1024 * 1024;
 
-    // read through the image
-    let mut buf = vec![0; 1024 * 1024];
+    let e01_reader = Arc::new(e01_reader);
     let mut offset = 0;
 
     let image_size_bs_disp = ByteSize::b(e01_reader.image_size)
         .display()
         .iec();
 
+    let mut batch = Vec::with_capacity(HASH_BATCH_SIZE);
+
     let mut prev_prog = Instant::now();
     let start = prev_prog;
-    while offset < e01_reader.image_size {
-        let read = e01_reader.read_at_offset(offset, &mut buf)?;
-        buf = hasher.update(buf, read);
-        offset += read as u64;
+    for chunk in e01_reader.stream_chunks(0..e01_reader.image_size) {
+        let chunk = chunk?;
+        offset += chunk.len() as u64;
+        batch.extend_from_slice(&chunk);
+
+        if batch.len() >= HASH_BATCH_SIZE {
+            let len = batch.len();
+            batch = hasher.update(batch, len);
+            batch.clear();
+        }
 
         if prev_prog.elapsed() > Duration::from_secs(2) {
             display_progress(
@@ -129,6 +267,11 @@ fn run(args: Args)-> Result<ExitCode, E01Error> {
         }
     }
 
+    if !batch.is_empty() {
+        let len = batch.len();
+        hasher.update(batch, len);
+    }
+
     display_progress(
         offset,
         e01_reader.image_size,
@@ -151,9 +294,26 @@ fn run(args: Args)-> Result<ExitCode, E01Error> {
         e01_reader.stored_sha1
     );
 
-    if let Some(sha256) = hashes.get(&HashType::SHA256) {
-        println!("{} {}", HashType::SHA256, hex::encode(sha256));
-    }
+    let sha256_check = match stored_sha256 {
+        Some(Ok(stored_sha256)) => check_hash(
+            HashType::SHA256,
+            hashes.get(&HashType::SHA256),
+            Some(stored_sha256)
+        ),
+        Some(Err(value)) => {
+            if let Some(sha256) = hashes.get(&HashType::SHA256) {
+                println!("{} {}", HashType::SHA256, hex::encode(sha256));
+            }
+            eprintln!("Stored SHA256 {} is not a valid hash", value);
+            Some(false)
+        },
+        None => {
+            if let Some(sha256) = hashes.get(&HashType::SHA256) {
+                println!("{} {}", HashType::SHA256, hex::encode(sha256));
+            }
+            None
+        }
+    };
 
     /*
        There is some tool which computes only the MD5 but instead of storing
@@ -171,7 +331,7 @@ fn run(args: Args)-> Result<ExitCode, E01Error> {
     }
 
     // combine the results and report
-    let check = [md5_check, sha1_check].into_iter()
+    let check = [md5_check, sha1_check, sha256_check].into_iter()
         .flatten()
         .reduce(|l, r| l && r);
 
@@ -0,0 +1,112 @@
+use std::io::Write;
+
+// Converts (first sector, sector count) pairs into sorted, disjoint byte
+// ranges within the image.
+fn error_byte_ranges(
+    errors: &[(u64, u64)],
+    sector_size: u64,
+    image_size: u64
+) -> Vec<(u64, u64)>
+{
+    let mut ranges = errors.iter()
+        .map(|&(first, count)| (
+            first.saturating_mul(sector_size).min(image_size),
+            first.saturating_add(count)
+                .saturating_mul(sector_size)
+                .min(image_size)
+        ))
+        .filter(|(beg, end)| beg < end)
+        .collect::<Vec<_>>();
+
+    ranges.sort_unstable();
+
+    // merge overlapping and adjacent ranges
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (beg, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if beg <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((beg, end))
+        }
+    }
+
+    merged
+}
+
+// Writes a GNU ddrescue mapfile marking the acquisition errors as bad
+// sectors and everything else as finished.
+pub fn write_ddrescue_mapfile<W: Write>(
+    mut out: W,
+    errors: &[(u64, u64)],
+    sector_size: u64,
+    image_size: u64
+) -> std::io::Result<()>
+{
+    writeln!(out, "# Mapfile. Created by e01-rs {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(out, "# current_pos  current_status  current_pass")?;
+    writeln!(out, "0x{:08X}     +               1", 0)?;
+    writeln!(out, "#      pos        size  status")?;
+
+    let mut pos = 0;
+    for (beg, end) in error_byte_ranges(errors, sector_size, image_size) {
+        if pos < beg {
+            writeln!(out, "0x{:08X}  0x{:08X}  +", pos, beg - pos)?;
+        }
+        writeln!(out, "0x{:08X}  0x{:08X}  -", beg, end - beg)?;
+        pos = end;
+    }
+
+    if pos < image_size {
+        writeln!(out, "0x{:08X}  0x{:08X}  +", pos, image_size - pos)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn error_byte_ranges_merge_and_clamp() {
+        let errors = [(10, 2), (0, 1), (11, 4), (15, 1), (98, 10)];
+
+        assert_eq!(
+            error_byte_ranges(&errors, 512, 100 * 512),
+            vec![
+                (0, 512),
+                (10 * 512, 16 * 512),
+                (98 * 512, 100 * 512)
+            ]
+        );
+    }
+
+    #[test]
+    fn write_ddrescue_mapfile_ok() {
+        let mut out = vec![];
+        write_ddrescue_mapfile(&mut out, &[(2, 1)], 512, 4096).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines = text.lines()
+            .filter(|l| !l.starts_with('#'))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            [
+                "0x00000000     +               1",
+                "0x00000000  0x00000400  +",
+                "0x00000400  0x00000200  -",
+                "0x00000600  0x00000A00  +"
+            ]
+        );
+    }
+
+    #[test]
+    fn write_ddrescue_mapfile_no_errors() {
+        let mut out = vec![];
+        write_ddrescue_mapfile(&mut out, &[], 512, 4096).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.ends_with("0x00000000  0x00001000  +\n"));
+    }
+}
@@ -0,0 +1,165 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaType {
+    Removable,
+    Fixed,
+    Optical,
+    // logical evidence files
+    SingleFiles,
+    Memory,
+    Unknown(u8)
+}
+
+impl From<u8> for MediaType {
+    fn from(v: u8) -> Self {
+        match v {
+            0x00 => Self::Removable,
+            0x01 => Self::Fixed,
+            0x03 => Self::Optical,
+            0x0e => Self::SingleFiles,
+            0x10 => Self::Memory,
+            _ => Self::Unknown(v)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MediaFlags(pub u8);
+
+impl MediaFlags {
+    const IMAGE: u8 = 0x01;
+    const PHYSICAL: u8 = 0x02;
+    const FASTBLOC: u8 = 0x04;
+    const TABLEAU: u8 = 0x08;
+
+    pub fn is_image(&self) -> bool {
+        self.0 & Self::IMAGE != 0
+    }
+
+    // a physical device, as opposed to a logical volume
+    pub fn is_physical(&self) -> bool {
+        self.0 & Self::PHYSICAL != 0
+    }
+
+    pub fn fastbloc(&self) -> bool {
+        self.0 & Self::FASTBLOC != 0
+    }
+
+    pub fn tableau(&self) -> bool {
+        self.0 & Self::TABLEAU != 0
+    }
+
+    // acquired through a Fastbloc or Tableau write blocker
+    pub fn is_write_blocked(&self) -> bool {
+        self.fastbloc() || self.tableau()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompressionLevel {
+    #[default]
+    None,
+    Good,
+    Best,
+    Unknown(u8)
+}
+
+impl From<u8> for CompressionLevel {
+    fn from(v: u8) -> Self {
+        match v {
+            0x00 => Self::None,
+            0x01 => Self::Good,
+            0x02 => Self::Best,
+            _ => Self::Unknown(v)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MediaInfo {
+    // None if the image doesn't say
+    pub media_type: Option<MediaType>,
+    pub media_flags: MediaFlags,
+    pub chs_cylinders: u32,
+    pub chs_heads: u32,
+    pub chs_sectors: u32,
+    pub compression_level: CompressionLevel,
+    pub error_granularity: u32,
+    pub palm_volume_start_sector: u32,
+    pub smart_logs_start_sector: u32,
+    pub set_identifier: [u8; 16]
+}
+
+impl MediaInfo {
+    // EWF2 device information sections give the drive type as a letter
+    // and whether the device is physical as a 0 or 1. They have no media
+    // flags, but are always of an image, as EWF1 flags it.
+    pub(crate) fn from_device_information(pairs: &[(String, String)]) -> Self {
+        let mut info = MediaInfo {
+            media_flags: MediaFlags(MediaFlags::IMAGE),
+            ..Default::default()
+        };
+
+        for (k, v) in pairs {
+            match (k.as_str(), v.trim()) {
+                ("dt", "r") => info.media_type = Some(MediaType::Removable),
+                ("dt", "f") => info.media_type = Some(MediaType::Fixed),
+                ("dt", "c") => info.media_type = Some(MediaType::Optical),
+                ("dt", "l") => info.media_type = Some(MediaType::SingleFiles),
+                ("dt", "m") => info.media_type = Some(MediaType::Memory),
+                ("ph", "1") => info.media_flags.0 |= MediaFlags::PHYSICAL,
+                _ => {}
+            }
+        }
+
+        info
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn media_flags_bits() {
+        let f = MediaFlags(0x0b);
+        assert!(f.is_image());
+        assert!(f.is_physical());
+        assert!(!f.fastbloc());
+        assert!(f.tableau());
+        assert!(f.is_write_blocked());
+
+        let f = MediaFlags(0x01);
+        assert!(!f.is_physical());
+        assert!(!f.is_write_blocked());
+    }
+
+    #[test]
+    fn media_type_from_u8() {
+        assert_eq!(MediaType::from(0x00), MediaType::Removable);
+        assert_eq!(MediaType::from(0x10), MediaType::Memory);
+        assert_eq!(MediaType::from(0x42), MediaType::Unknown(0x42));
+    }
+
+    #[test]
+    fn from_device_information_ok() {
+        let info = MediaInfo::from_device_information(&[
+            ("dt".into(), "r".into()),
+            ("ph".into(), "1".into()),
+            ("sn".into(), "S123".into())
+        ]);
+
+        assert_eq!(info.media_type, Some(MediaType::Removable));
+        assert!(info.media_flags.is_image());
+        assert!(info.media_flags.is_physical());
+    }
+
+    #[test]
+    fn from_device_information_no_type() {
+        let info = MediaInfo::from_device_information(&[
+            ("sn".into(), "S123".into())
+        ]);
+
+        assert_eq!(info.media_type, None);
+        assert_eq!(info.media_flags, MediaFlags(0x01));
+    }
+}
@@ -1,19 +1,33 @@
-use flate2::read::ZlibDecoder;
+use flate2::{Decompress, FlushDecompress};
 use simd_adler32::read::adler32;
-use std::io::{Cursor, Read};
+use std::io::Cursor;
 use tracing::{debug, error};
 
 use crate::workersource::WorkerSource;
 use crate::e01_reader::{CorruptChunkPolicy, ReadErrorKind};
 use crate::sec_read::Chunk;
+use crate::segment::CompressionMethod;
 
-#[derive(Debug)]
 pub struct ReadWorker {
     chunk_size: usize,
     image_end: u64,
     corrupt_chunk_policy: CorruptChunkPolicy,
+    // for decompressing parts of chunks; handed over by read_whole
     scratch: Vec<u8>,
-    decoder: ZlibDecoder<Cursor<Vec<u8>>>
+    // the raw data of the chunk being read
+    raw: Vec<u8>,
+    decoder: Decompress
+}
+
+impl std::fmt::Debug for ReadWorker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the decompressor has nothing worth showing
+        f.debug_struct("ReadWorker")
+            .field("chunk_size", &self.chunk_size)
+            .field("image_end", &self.image_end)
+            .field("corrupt_chunk_policy", &self.corrupt_chunk_policy)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Clone for ReadWorker {
@@ -38,7 +52,8 @@ impl ReadWorker {
             image_end,
             corrupt_chunk_policy,
             scratch: vec![0; chunk_size],
-            decoder: ZlibDecoder::new(Cursor::new(vec![0; chunk_size + 4]))
+            raw: vec![0; chunk_size + 4],
+            decoder: Decompress::new(true)
         }
     }
 
@@ -49,48 +64,49 @@ impl ReadWorker {
         chunk_len: usize
     ) -> Result<(), ReadErrorKind>
     {
-        // take the buffer from the decoder
-        let cur = self.decoder.reset(Cursor::new(vec![0; 0]));
-        let mut v = cur.into_inner();
-        let raw_data = &mut v[..chunk_len];
-
-        // do the read
-        let r = src.read(chunk_off, raw_data)
-            .map_err(ReadErrorKind::IoError);
-
-        // give the buffer back to the decoder
-        self.decoder.reset(Cursor::new(v));
-
-        r
+        src.read(chunk_off, &mut self.raw[..chunk_len])
+            .map_err(ReadErrorKind::IoError)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn read_compressed_decompress(
         &mut self,
         chunk_index: usize,
-        _chunk_len: usize,
+        chunk_len: usize,
+        compr_method: CompressionMethod,
         buf: &mut [u8],
         beg_in_chunk: usize,
         end_in_chunk: usize
     ) -> Result<(), ReadErrorKind>
     {
-        // Every chunk contains the same amount of data except for the last
-        // one; decompress directly into the buffer if there is sufficient
-        // space.
+        // Decompression stops at the end of the requested portion, so if
+        // it starts the chunk, decompress directly into the buffer.
 
-        let (out, use_scratch) = if buf.len() == self.chunk_size ||
-            (buf.len() < self.chunk_size &&
-            (chunk_index * self.chunk_size) as u64 > self.image_end)
-        {
+        let (out, use_scratch) = if beg_in_chunk == 0 {
             // decompress directly into output buffer
             (&mut buf[..], false)
         }
         else {
-            // decompress into scratch buffer
-            (&mut self.scratch[..buf.len()], true)
+            // decompress into scratch buffer, up to the end of the
+            // requested portion; it may have been handed over
+            if self.scratch.len() < end_in_chunk {
+                self.scratch.resize(self.chunk_size, 0);
+            }
+            (&mut self.scratch[..end_in_chunk], true)
+        };
+
+        let raw_data = &self.raw[..chunk_len];
+
+        let r = match compr_method {
+            CompressionMethod::Bzip => bzip_decompress_into(raw_data, out),
+            // EWF1 has only zlib compression
+            CompressionMethod::Deflate |
+            CompressionMethod::None =>
+                zlib_decompress_into(&mut self.decoder, raw_data, out)
         };
 
         // compressed chunks are either ok or unrecoverable
-        if let Err(e) = self.decoder.read_exact(out) {
+        if let Err(e) = r {
             error!("decompression failed for chunk {}: {}", chunk_index, e);
             match self.corrupt_chunk_policy {
                 CorruptChunkPolicy::Error => return Err(
@@ -120,6 +136,7 @@ impl ReadWorker {
         chunk_index: usize,
         chunk_off: u64,
         chunk_len: usize,
+        compr_method: CompressionMethod,
         buf: &mut [u8],
         beg_in_chunk: usize,
         end_in_chunk: usize
@@ -129,6 +146,7 @@ impl ReadWorker {
         self.read_compressed_decompress(
             chunk_index,
             chunk_len,
+            compr_method,
             buf,
             beg_in_chunk,
             end_in_chunk
@@ -142,39 +160,33 @@ impl ReadWorker {
         chunk_index: usize,
         chunk_off: u64,
         chunk_len: usize,
+        has_checksum: bool,
         buf: &mut [u8],
         beg_in_chunk: usize,
         end_in_chunk: usize
     ) -> Result<(), ReadErrorKind>
     {
-        // take the buffer from the decoder
-        let cur = self.decoder.reset(Cursor::new(vec![0; 0]));
-        let mut v = cur.into_inner();
-        let raw_data = &mut v[..chunk_len];
-
         // do the read
-        self.read_uncompressed_inner(
+        Self::read_uncompressed_inner(
+            self.corrupt_chunk_policy,
             src,
             chunk_index,
             chunk_off,
+            has_checksum,
             buf,
             beg_in_chunk,
             end_in_chunk,
-            raw_data
-        )?;
-
-        // give the buffer back to the decoder
-        self.decoder.reset(Cursor::new(v));
-
-        Ok(())
+            &mut self.raw[..chunk_len]
+        )
     }
 
     #[allow(clippy::too_many_arguments)]
     fn read_uncompressed_inner<WS: WorkerSource>(
-        &mut self,
+        corrupt_chunk_policy: CorruptChunkPolicy,
         src: &mut WS,
         chunk_index: usize,
         chunk_off: u64,
+        has_checksum: bool,
         buf: &mut [u8],
         beg_in_chunk: usize,
         end_in_chunk: usize,
@@ -184,6 +196,11 @@ impl ReadWorker {
         src.read(chunk_off, raw_data)
             .map_err(ReadErrorKind::IoError)?;
 
+        if !has_checksum {
+            buf.copy_from_slice(&raw_data[beg_in_chunk..end_in_chunk]);
+            return Ok(());
+        }
+
         let raw_data_len = raw_data.len();
         if raw_data_len < 5 {
             return Err(ReadErrorKind::TooShort(chunk_index, raw_data_len));
@@ -207,7 +224,7 @@ impl ReadWorker {
         // deal with checksum mismatch
         if crc != crc_stored {
             error!("checksum mismatch reading chunk {}", chunk_index);
-            match self.corrupt_chunk_policy {
+            match corrupt_chunk_policy {
                 CorruptChunkPolicy::Error => return Err(
                     ReadErrorKind::BadChecksum(chunk_index, crc_stored, crc)
                 ),
@@ -226,9 +243,11 @@ impl ReadWorker {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn read<WS: WorkerSource>(
         &mut self,
         chunk: &Chunk,
+        compr_method: CompressionMethod,
         src: &mut WS,
         chunk_index: usize,
         buf: &mut [u8],
@@ -241,6 +260,14 @@ impl ReadWorker {
 
         debug!("reading chunk {chunk_index} [{beg_in_chunk},{end_in_chunk})");
 
+        // fill the buffer with the pattern, if the chunk is one
+        if let Some(pattern) = chunk.pattern {
+            buf.iter_mut()
+                .zip(pattern.iter().cycle().skip(beg_in_chunk % pattern.len()))
+                .for_each(|(b, p)| *b = *p);
+            return Ok(());
+        }
+
         // read the data into the buffer
         if chunk.compressed {
             self.read_compressed(
@@ -248,6 +275,7 @@ impl ReadWorker {
                 chunk_index,
                 chunk_off,
                 chunk_len,
+                compr_method,
                 buf,
                 beg_in_chunk,
                 end_in_chunk
@@ -259,10 +287,261 @@ impl ReadWorker {
                 chunk_index,
                 chunk_off,
                 chunk_len,
+                chunk.has_checksum,
                 buf,
                 beg_in_chunk,
                 end_in_chunk
             )
         }
     }
+
+    // Reads the whole chunk, of chunk_len bytes, into the scratch buffer,
+    // and hands it over, e.g., to be cached; give it a spare buffer with
+    // replace_scratch to use instead, such as one evicted from a cache.
+    pub fn read_whole<WS: WorkerSource>(
+        &mut self,
+        chunk: &Chunk,
+        compr_method: CompressionMethod,
+        src: &mut WS,
+        chunk_index: usize,
+        chunk_len: usize
+    ) -> Result<Vec<u8>, ReadErrorKind>
+    {
+        let mut ch = std::mem::take(&mut self.scratch);
+        ch.resize(chunk_len, 0);
+
+        match self.read(chunk, compr_method, src, chunk_index, &mut ch, 0, chunk_len) {
+            Ok(()) => Ok(ch),
+            Err(e) => {
+                self.scratch = ch;
+                Err(e)
+            }
+        }
+    }
+
+    pub fn replace_scratch(&mut self, buf: Vec<u8>) {
+        self.scratch = buf;
+    }
+}
+
+// Fills out from the zlib stream in data.
+fn zlib_decompress_into(
+    zlib: &mut Decompress,
+    data: &[u8],
+    out: &mut [u8]
+) -> Result<(), std::io::Error>
+{
+    zlib.reset(true);
+
+    let mut produced = 0;
+
+    while produced < out.len() {
+        let (in_before, out_before) = (zlib.total_in(), zlib.total_out());
+        let consumed = in_before as usize;
+
+        let status = zlib.decompress(
+            &data[consumed..],
+            &mut out[produced..],
+            FlushDecompress::None
+        )
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        produced += (zlib.total_out() - out_before) as usize;
+
+        let stalled = zlib.total_in() == in_before &&
+            zlib.total_out() == out_before;
+
+        if produced < out.len() &&
+            (status == flate2::Status::StreamEnd || stalled)
+        {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+
+    Ok(())
+}
+
+// Fills out from the bzip2 stream in data. libbzip2 can't restart a stream
+// once it has begun, so each chunk needs fresh state.
+fn bzip_decompress_into(
+    data: &[u8],
+    out: &mut [u8]
+) -> Result<(), std::io::Error>
+{
+    let mut bzip = bzip2::Decompress::new(false);
+    let mut produced = 0;
+
+    while produced < out.len() {
+        let (in_before, out_before) = (bzip.total_in(), bzip.total_out());
+        let consumed = in_before as usize;
+
+        let status = bzip.decompress(&data[consumed..], &mut out[produced..])
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        produced += (bzip.total_out() - out_before) as usize;
+
+        let stalled = bzip.total_in() == in_before &&
+            bzip.total_out() == out_before;
+
+        if produced < out.len() &&
+            (status == bzip2::Status::StreamEnd || stalled)
+        {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bzip2::write::BzEncoder;
+    use flate2::{Compression, write::ZlibEncoder};
+    use std::io::Write;
+
+    struct VecSource(Vec<u8>);
+
+    impl WorkerSource for VecSource {
+        fn read(
+            &mut self,
+            off: u64,
+            buf: &mut [u8]
+        ) -> Result<(), std::io::Error>
+        {
+            let off = off as usize;
+            buf.copy_from_slice(&self.0[off..off + buf.len()]);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read_compressed_middle_of_chunk() {
+        let data = (0..64).map(|i| i % 8 + 1).collect::<Vec<u8>>();
+
+        let mut enc = ZlibEncoder::new(vec![], Compression::default());
+        enc.write_all(&data).unwrap();
+        let mut src = VecSource(enc.finish().unwrap());
+
+        let chunk = Chunk {
+            segment: 0,
+            data_offset: 0,
+            end_offset: src.0.len() as u64,
+            compressed: true,
+            has_checksum: false,
+            pattern: None,
+            compression_unknown: false
+        };
+
+        let mut w = ReadWorker::new(64, 1000, CorruptChunkPolicy::Error);
+        let mut buf = [0; 32];
+        w.read(
+            &chunk,
+            CompressionMethod::Deflate,
+            &mut src,
+            0,
+            &mut buf,
+            16,
+            48
+        ).unwrap();
+        assert_eq!(buf, data[16..48]);
+    }
+
+    #[test]
+    fn read_compressed_truncated() {
+        let data = (0..64).map(|i| i % 8 + 1).collect::<Vec<u8>>();
+
+        let mut enc = ZlibEncoder::new(vec![], Compression::default());
+        enc.write_all(&data).unwrap();
+        let mut src = VecSource(enc.finish().unwrap());
+
+        let chunk = Chunk {
+            segment: 0,
+            data_offset: 0,
+            end_offset: src.0.len() as u64 / 2,
+            compressed: true,
+            has_checksum: false,
+            pattern: None,
+            compression_unknown: false
+        };
+
+        let mut w = ReadWorker::new(64, 1000, CorruptChunkPolicy::Error);
+        let mut buf = [0; 64];
+        assert!(matches!(
+            w.read(
+                &chunk,
+                CompressionMethod::Deflate,
+                &mut src,
+                0,
+                &mut buf,
+                0,
+                64
+            ),
+            Err(ReadErrorKind::DecompressionFailed(0, _))
+        ));
+    }
+
+    fn bzip_chunk(data: &[u8]) -> (VecSource, Chunk) {
+        let mut enc = BzEncoder::new(vec![], bzip2::Compression::default());
+        enc.write_all(data).unwrap();
+        let src = VecSource(enc.finish().unwrap());
+
+        let chunk = Chunk {
+            segment: 0,
+            data_offset: 0,
+            end_offset: src.0.len() as u64,
+            compressed: true,
+            has_checksum: false,
+            pattern: None,
+            compression_unknown: false
+        };
+
+        (src, chunk)
+    }
+
+    #[test]
+    fn read_bzip_reuses_worker() {
+        let data = (0..64).map(|i| i % 8 + 1).collect::<Vec<u8>>();
+        let (mut src, chunk) = bzip_chunk(&data);
+
+        let mut w = ReadWorker::new(128, 1000, CorruptChunkPolicy::Error);
+
+        // the whole chunk, then part of it
+        for (beg, end) in [(0, 64), (16, 48)] {
+            let mut buf = vec![0; end - beg];
+            w.read(
+                &chunk,
+                CompressionMethod::Bzip,
+                &mut src,
+                0,
+                &mut buf,
+                beg,
+                end
+            ).unwrap();
+            assert_eq!(buf, data[beg..end]);
+        }
+    }
+
+    #[test]
+    fn read_bzip_truncated() {
+        let data = (0..64).map(|i| i % 8 + 1).collect::<Vec<u8>>();
+        let (mut src, mut chunk) = bzip_chunk(&data);
+        chunk.end_offset /= 2;
+
+        let mut w = ReadWorker::new(128, 1000, CorruptChunkPolicy::Error);
+        let mut buf = [0; 64];
+        assert!(matches!(
+            w.read(
+                &chunk,
+                CompressionMethod::Bzip,
+                &mut src,
+                0,
+                &mut buf,
+                0,
+                64
+            ),
+            Err(ReadErrorKind::DecompressionFailed(0, _))
+        ));
+    }
 }
@@ -0,0 +1,259 @@
+use futures::future::{BoxFuture, FutureExt};
+use std::{
+    sync::Arc,
+    time::Duration
+};
+use tracing::warn;
+
+use crate::{
+    bytessource::{BytesSource, is_transient},
+    e01_reader::RetryOptions
+};
+
+// Wraps a remote source so that a transiently failed or timed out read is
+// retried with exponential backoff, instead of failing the whole read of
+// the image. Other failures, e.g., missing objects, are returned at once.
+pub struct RetrySource {
+    inner: Arc<dyn BytesSource + Send + Sync>,
+    options: RetryOptions
+}
+
+impl RetrySource {
+    pub fn new(
+        inner: Arc<dyn BytesSource + Send + Sync>,
+        options: RetryOptions
+    ) -> Self
+    {
+        Self {
+            inner,
+            options
+        }
+    }
+}
+
+// Returns the delay before the given retry, which is drawn uniformly from
+// [0, min(initial * 2^retry, max)] so that readers which failed together
+// don't all retry together.
+fn backoff(options: &RetryOptions, retry: u32) -> Duration {
+    let cap = options.initial_backoff
+        .saturating_mul(2_u32.saturating_pow(retry))
+        .min(options.max_backoff);
+
+    cap.mul_f64(rand::random::<f64>())
+}
+
+fn timed_out(what: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::TimedOut, what)
+}
+
+impl BytesSource for RetrySource {
+    fn read(
+        &self,
+        beg: u64,
+        end: u64
+    ) -> BoxFuture<'static, Result<Vec<u8>, std::io::Error>>
+    {
+        let inner = Arc::clone(&self.inner);
+        let options = self.options.clone();
+
+        async move {
+            let attempts = async {
+                let mut retry = 0;
+                loop {
+                    let r = match options.request_timeout {
+                        Some(t) => tokio::time::timeout(t, inner.read(beg, end))
+                            .await
+                            .unwrap_or_else(|_| Err(timed_out("request timed out"))),
+                        None => inner.read(beg, end).await
+                    };
+
+                    match r {
+                        Err(e) if retry < options.max_retries && is_transient(&e) => {
+                            let delay = backoff(&options, retry);
+                            retry += 1;
+                            warn!(
+                                "read [{beg},{end}) failed: {e}; retry {retry}/{} in {delay:?}",
+                                options.max_retries
+                            );
+                            tokio::time::sleep(delay).await;
+                        },
+                        r => return r
+                    }
+                }
+            };
+
+            match options.deadline {
+                Some(d) => tokio::time::timeout(d, attempts)
+                    .await
+                    .unwrap_or_else(|_| Err(timed_out("read deadline exceeded"))),
+                None => attempts.await
+            }
+        }.boxed()
+    }
+
+    fn end(&self) -> u64 {
+        self.inner.end()
+    }
+
+    fn version(&self) -> Option<String> {
+        self.inner.version()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use crate::bytessource::transient_error;
+
+    // fails the given number of reads with the given error, then succeeds
+    struct FlakySource {
+        failures: AtomicU32,
+        error: fn() -> std::io::Error
+    }
+
+    impl FlakySource {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures: AtomicU32::new(failures),
+                error: || transient_error("503 Slow Down")
+            }
+        }
+    }
+
+    impl BytesSource for FlakySource {
+        fn read(
+            &self,
+            beg: u64,
+            end: u64
+        ) -> BoxFuture<'static, Result<Vec<u8>, std::io::Error>>
+        {
+            let failed = self.failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            let error = self.error;
+
+            async move {
+                if failed {
+                    Err(error())
+                }
+                else {
+                    Ok((beg..end).map(|i| i as u8).collect())
+                }
+            }.boxed()
+        }
+
+        fn end(&self) -> u64 {
+            256
+        }
+    }
+
+    struct StuckSource;
+
+    impl BytesSource for StuckSource {
+        fn read(
+            &self,
+            _beg: u64,
+            _end: u64
+        ) -> BoxFuture<'static, Result<Vec<u8>, std::io::Error>>
+        {
+            futures::future::pending().boxed()
+        }
+
+        fn end(&self) -> u64 {
+            256
+        }
+    }
+
+    fn options(max_retries: u32) -> RetryOptions {
+        RetryOptions {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            request_timeout: Some(Duration::from_millis(50)),
+            deadline: None
+        }
+    }
+
+    fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        tokio::runtime::Runtime::new().unwrap().block_on(f)
+    }
+
+    #[test]
+    fn backoff_capped() {
+        let options = options(10);
+        for retry in 0..10 {
+            assert!(backoff(&options, retry) <= Duration::from_millis(4));
+        }
+        assert!(backoff(&options, u32::MAX) <= Duration::from_millis(4));
+    }
+
+    #[test]
+    fn read_retries_then_succeeds() {
+        let src = RetrySource::new(
+            Arc::new(FlakySource::new(3)),
+            options(3)
+        );
+
+        assert_eq!(block_on(src.read(4, 8)).unwrap(), [4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn read_retries_exhausted() {
+        let src = RetrySource::new(
+            Arc::new(FlakySource::new(3)),
+            options(2)
+        );
+
+        assert_eq!(
+            block_on(src.read(4, 8)).unwrap_err().to_string(),
+            "503 Slow Down"
+        );
+    }
+
+    #[test]
+    fn read_permanent_error_not_retried() {
+        let src = Arc::new(FlakySource {
+            error: || std::io::Error::new(std::io::ErrorKind::NotFound, "404 Not Found"),
+            ..FlakySource::new(1)
+        });
+        let retry = RetrySource::new(src.clone(), options(3));
+
+        assert_eq!(
+            block_on(retry.read(4, 8)).unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+
+        // the one failure was used up, without retrying
+        assert_eq!(block_on(retry.read(4, 8)).unwrap(), [4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn read_request_timeout() {
+        let src = RetrySource::new(Arc::new(StuckSource), options(1));
+
+        assert_eq!(
+            block_on(src.read(4, 8)).unwrap_err().kind(),
+            std::io::ErrorKind::TimedOut
+        );
+    }
+
+    #[test]
+    fn read_deadline() {
+        let src = RetrySource::new(
+            Arc::new(StuckSource),
+            RetryOptions {
+                request_timeout: None,
+                deadline: Some(Duration::from_millis(20)),
+                ..options(5)
+            }
+        );
+
+        assert_eq!(
+            block_on(src.read(4, 8)).unwrap_err().to_string(),
+            "read deadline exceeded"
+        );
+    }
+}
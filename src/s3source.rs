@@ -1,66 +1,52 @@
 use futures::future::{BoxFuture, FutureExt};
+use reqwest::StatusCode;
 use s3::{
     bucket::Bucket,
-    creds::Credentials,
-    region::Region,
+    error::S3Error,
     request::request_trait::ResponseData,
 };
 use tracing::trace;
 
-use crate::bytessource::BytesSource;
+use crate::{
+    bytessource::BytesSource,
+    httpsource::{request_error, status_error}
+};
 
 pub struct S3Source {
     bucket: Bucket,
     path: String,
-    len: u64
+    len: u64,
+    // the object's ETag, or failing that, its last modification time
+    version: Option<String>
 }
 
 impl S3Source {
     pub fn new(
         bucket: Bucket,
         path: String,
-        len: u64
+        len: u64,
+        version: Option<String>
     ) -> Self
     {
         Self {
             bucket,
             path,
-            len
+            len,
+            version
         }
     }
+}
 
-/*
-    pub fn new(
-        name: &str,
-        region: &str,
-        endpoint: &str,
-        path: &str
-    ) -> Self {
-        let bucket = *Bucket::new(
-            name,
-            Region::Custom {
-                region: region.into(),
-                endpoint: endpoint.into()
-            },
-            Credentials::anonymous().unwrap()
-        )
-        .unwrap()
-        .with_path_style();
-
-        let (h, code) = bucket.head_object(path)
-            .await
-            .unwrap();
-
-        assert_eq!(code, 200);
-        let len = h.content_length.unwrap().try_into().unwrap();
-
-        Self {
-            bucket,
-            path: path.into(),
-            len
-        }
+// Returns an error for a failed request, classified as for HTTP sources
+pub fn s3_error(e: S3Error) -> std::io::Error {
+    match e {
+        S3Error::HttpFailWithBody(code, _) => match StatusCode::from_u16(code) {
+            Ok(status) => status_error(status, e),
+            Err(_) => std::io::Error::other(e)
+        },
+        S3Error::Reqwest(e) => request_error(e),
+        e => std::io::Error::other(e)
     }
-*/
 }
 
 impl BytesSource for S3Source {
@@ -82,11 +68,15 @@ impl BytesSource for S3Source {
             .await
             .inspect(|_| trace!("read [{beg},{end}) from S3"))
             .map(ResponseData::to_vec)
-            .map_err(std::io::Error::other)
+            .map_err(s3_error)
         }.boxed()
     }
 
     fn end(&self) -> u64 {
         self.len
     }
+
+    fn version(&self) -> Option<String> {
+        self.version.clone()
+    }
 }
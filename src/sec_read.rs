@@ -1,36 +1,72 @@
+use crate::case_metadata::{CaseMetadata, decode_utf16le};
 use crate::error::{IoError, LibError};
+use crate::media_info::{MediaFlags, MediaInfo};
+use crate::sec_read_v2::{CaseData, DeviceInformation};
+use crate::xmlsection::parse_flat_xml;
 use crate::generated::{
     ewf_digest_section::EwfDigestSection,
+    ewf_error2_header::EwfError2Header,
     ewf_hash_section::EwfHashSection,
+    ewf_ltree_header::EwfLtreeHeader,
     ewf_section_descriptor_v1::EwfSectionDescriptorV1,
+    ewf_session_header::EwfSessionHeader,
     ewf_table_header::EwfTableHeader,
     ewf_volume::EwfVolume,
     ewf_volume_smart::EwfVolumeSmart
 };
 //use crate::generated::ewf_section_descriptor_v2::*;
 
+use flate2::read::ZlibDecoder;
 use kaitai::{BytesReader, KStream, KStruct};
+use md5::{Digest, Md5};
+use std::io::Read;
+use tracing::{debug, warn};
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Chunk {
     pub segment: usize,
     pub data_offset: u64,
     pub end_offset: u64,
-    pub compressed: bool
+    pub compressed: bool,
+    pub has_checksum: bool,
+    // EWF2 chunks may be an 8-byte pattern repeated instead of stored data
+    pub pattern: Option<[u8; 8]>,
+    // the table entry lost its compression flag to offset overflow, so
+    // compression must be inferred from the chunk size
+    pub compression_unknown: bool
 }
 
 #[derive(Debug)]
 pub enum Section {
+    Header(CaseMetadata),
+    Header2(CaseMetadata),
+    XHeader(CaseMetadata),
     Volume(VolumeSection),
-    Table(Vec<Chunk>),
+    // table sections are kept even when unreadable, as the table2 section
+    // following each holds a backup copy
+    Table(Result<Vec<Chunk>, LibError>),
+    Table2(Result<Vec<Chunk>, LibError>),
+    // EWF2 sector tables have complete chunk extents
+    SectorTable(Vec<Chunk>),
+    DeviceInformation(DeviceInformation),
+    CaseData(CaseData),
     Sectors(u64),
     Hash([u8; 16]),
     Digest([u8; 16], [u8; 20]),
+    Sha1([u8; 20]),
+    XHash(Vec<(String, String)>),
+    // (first sector, sector count) of sectors which could not be read
+    // during acquisition
+    Error(Vec<(u64, u64)>),
+    // (start sector, flags) of each session or track on optical media
+    Session(Vec<(u64, u32)>),
+    // the file tree of a logical evidence file, as text
+    LTree(String),
     Done,
     Other
 }
 
-fn checksum_reader(
+pub fn checksum_reader(
     reader: &BytesReader,
     len: usize
 ) -> Result<u32, IoError>
@@ -42,7 +78,7 @@ fn checksum_reader(
     ))?)
 }
 
-fn checksum_ok(
+pub fn checksum_ok(
     section_type: &str,
     io: &BytesReader,
     section_io: &BytesReader,
@@ -61,6 +97,8 @@ fn read_section(
     ignore_checksums: bool
 ) -> Result<(usize, Section), LibError> {
 
+    let section_start = io.pos() as u64;
+
     let sd = EwfSectionDescriptorV1::read_into::<_, EwfSectionDescriptorV1>(io, None, None)
         .map_err(|e| LibError::DeserializationFailed("EwfFileHeaderV1", e))?;
 
@@ -76,16 +114,49 @@ fn read_section(
     let section_type = section_type_full.trim_matches(char::from(0));
 
     let section = match section_type {
+        "header" => metadata_section(
+            "header",
+            read_compressed_section("header", io, section_size)
+                .and_then(|data| CaseMetadata::from_header(&data))
+                .map(Section::Header),
+            ignore_checksums
+        )?,
+        "header2" => metadata_section(
+            "header2",
+            read_compressed_section("header2", io, section_size)
+                .and_then(|data| CaseMetadata::from_header2(&data))
+                .map(Section::Header2),
+            ignore_checksums
+        )?,
+        "xheader" => metadata_section(
+            "xheader",
+            read_compressed_section("xheader", io, section_size)
+                .and_then(|data| CaseMetadata::from_xheader(&data))
+                .map(Section::XHeader),
+            ignore_checksums
+        )?,
         "disk" | "volume" =>
             Section::Volume(VolumeSection::new(io, section_size, ignore_checksums)?),
         "table" =>
-            Section::Table(read_table(io, section_size, ignore_checksums)?),
+            Section::Table(read_table(io, section_start, ignore_checksums)),
+        "table2" =>
+            Section::Table2(read_table(io, section_start, ignore_checksums)),
         "sectors" => Section::Sectors(io.pos() as u64 + section_size),
         "hash" => Section::Hash(read_hash_section(io, ignore_checksums)?),
         "digest" => {
             let (md5, sha1) = read_digest_section(io, ignore_checksums)?;
             Section::Digest(md5, sha1)
         },
+        "xhash" => metadata_section(
+            "xhash",
+            read_compressed_section("xhash", io, section_size)
+                .and_then(|data| read_xhash_section(&data))
+                .map(Section::XHash),
+            ignore_checksums
+        )?,
+        "error2" => Section::Error(read_error2_section(io, ignore_checksums)?),
+        "session" => Section::Session(read_session_section(io, ignore_checksums)?),
+        "ltree" => Section::LTree(read_ltree_section(io, ignore_checksums)?),
         "done" => Section::Done,
         _ => Section::Other
     };
@@ -95,6 +166,53 @@ fn read_section(
     Ok((section_offset, section))
 }
 
+// Case metadata and stored hashes have no bearing on the image data, so a
+// metadata section which can't be read is skipped rather than failing the
+// open, unless corrupt sections are errors.
+fn metadata_section(
+    section_type: &str,
+    section: Result<Section, LibError>,
+    ignore_checksums: bool
+) -> Result<Section, LibError>
+{
+    match section {
+        Err(e) if ignore_checksums => {
+            warn!("skipping unreadable {section_type} section: {e}");
+            Ok(Section::Other)
+        },
+        r => r
+    }
+}
+
+pub fn read_compressed_section(
+    section_type: &'static str,
+    io: &BytesReader,
+    size: u64
+) -> Result<Vec<u8>, LibError> {
+    let data = io.read_bytes(size as usize)
+        .map_err(IoError::Read)?;
+
+    let mut buf = vec![];
+    ZlibDecoder::new(&data[..])
+        .read_to_end(&mut buf)
+        .map_err(|e| LibError::DecompressionFailed(section_type, e))?;
+
+    Ok(buf)
+}
+
+fn read_xhash_section(data: &[u8]) -> Result<Vec<(String, String)>, LibError> {
+    let text = String::from_utf8_lossy(data);
+
+    let hashes = parse_flat_xml(&text, "xhash")
+        .map_err(|e| LibError::MalformedSection("xhash", e))?
+        .into_iter()
+        // normalize the hash names and values
+        .map(|(k, v)| (k.to_ascii_lowercase(), v.to_ascii_lowercase()))
+        .collect();
+
+    Ok(hashes)
+}
+
 fn read_hash_section(
     io: &BytesReader,
     ignore_checksums: bool,
@@ -120,6 +238,135 @@ fn read_hash_section(
     Ok(md5)
 }
 
+fn read_error2_section(
+    io: &BytesReader,
+    ignore_checksums: bool
+) -> Result<Vec<(u64, u64)>, LibError> {
+    let error_header = EwfError2Header::read_into::<_, EwfError2Header>(io, None, None)
+        .map_err(|e| LibError::DeserializationFailed("EwfError2Header", e))?;
+
+    if !ignore_checksums {
+        checksum_ok(
+            "Error2 section",
+            io,
+            &error_header._io(),
+            *error_header.checksum(),
+        )?;
+    }
+
+    let entry_count = *error_header.number_of_entries() as usize;
+    if entry_count == 0 {
+        return Ok(vec![]);
+    }
+
+    let io_entries = Clone::clone(io);
+
+    let errors = (0..entry_count)
+        .map(|_| Ok((
+            io.read_u4le().map_err(IoError::Read)? as u64,
+            io.read_u4le().map_err(IoError::Read)? as u64
+        )))
+        .collect::<Result<Vec<_>, LibError>>()?;
+
+    if !ignore_checksums {
+        // error2 footer
+        let crc_stored = io.read_u4le().map_err(IoError::Read)?;
+        let crc = checksum_reader(&io_entries, entry_count * 8)?;
+
+        if crc != crc_stored {
+            return Err(LibError::BadChecksum(
+                "Error2 entries".into(),
+                crc,
+                crc_stored
+            ));
+        }
+    }
+
+    Ok(errors)
+}
+
+fn read_session_section(
+    io: &BytesReader,
+    ignore_checksums: bool
+) -> Result<Vec<(u64, u32)>, LibError> {
+    let session_header = EwfSessionHeader::read_into::<_, EwfSessionHeader>(io, None, None)
+        .map_err(|e| LibError::DeserializationFailed("EwfSessionHeader", e))?;
+
+    if !ignore_checksums {
+        checksum_ok(
+            "Session section",
+            io,
+            &session_header._io(),
+            *session_header.checksum(),
+        )?;
+    }
+
+    let entry_count = *session_header.number_of_entries() as usize;
+    if entry_count == 0 {
+        return Ok(vec![]);
+    }
+
+    let io_entries = Clone::clone(io);
+
+    let sessions = (0..entry_count)
+        .map(|_| {
+            let flags = io.read_u4le().map_err(IoError::Read)?;
+            let start_sector = io.read_u4le().map_err(IoError::Read)? as u64;
+            // unknown
+            io.read_bytes(24).map_err(IoError::Read)?;
+            Ok((start_sector, flags))
+        })
+        .collect::<Result<Vec<_>, LibError>>()?;
+
+    if !ignore_checksums {
+        // session footer
+        let crc_stored = io.read_u4le().map_err(IoError::Read)?;
+        let crc = checksum_reader(&io_entries, entry_count * 32)?;
+
+        if crc != crc_stored {
+            return Err(LibError::BadChecksum(
+                "Session entries".into(),
+                crc,
+                crc_stored
+            ));
+        }
+    }
+
+    Ok(sessions)
+}
+
+fn read_ltree_section(
+    io: &BytesReader,
+    ignore_checksums: bool
+) -> Result<String, LibError> {
+    let io_header = Clone::clone(io);
+
+    let ltree_header = EwfLtreeHeader::read_into::<_, EwfLtreeHeader>(io, None, None)
+        .map_err(|e| LibError::DeserializationFailed("EwfLtreeHeader", e))?;
+
+    if !ignore_checksums {
+        // the checksum covers the integrity hash and data size
+        let crc_stored = *ltree_header.checksum();
+        let crc = checksum_reader(&io_header, 24)?;
+
+        if crc != crc_stored {
+            return Err(LibError::BadChecksum("Ltree section".into(), crc, crc_stored));
+        }
+    }
+
+    let data = io.read_bytes(*ltree_header.data_size() as usize)
+        .map_err(IoError::Read)?;
+
+    if !ignore_checksums && Md5::digest(&data[..])[..] != ltree_header.integrity_hash()[..] {
+        return Err(LibError::MalformedSection(
+            "ltree",
+            "integrity hash mismatch".into()
+        ));
+    }
+
+    Ok(decode_utf16le(&data))
+}
+
 fn read_digest_section(
     io: &BytesReader,
     ignore_checksums: bool,
@@ -149,26 +396,80 @@ fn read_digest_section(
     Ok((md5, sha1))
 }
 
-fn read_table_entry(
-    io: &BytesReader,
-    table_offset: u64
-) -> Result<Chunk, LibError>
+// Whether the offsets of a table are past the 2 GiB mark from its first
+// one, so that none of their top bits indicate compression. Chunk data runs
+// up to the table section which follows it, so this is so if the last
+// offset, taken whole, still precedes the table; read without its top bit,
+// it would fall more than 2 GiB short of the table.
+fn table_starts_overflowed(
+    entries: &[u32],
+    table_offset: u64,
+    section_start: u64
+) -> bool
 {
-    let entry = io.read_u4le().map_err(IoError::Read)?;
+    let Some(&last) = entries.last() else {
+        return false;
+    };
+
+    entries.iter().all(|e| e & 0x80000000 != 0) &&
+        entries.windows(2).all(|w| w[0] < w[1]) &&
+        table_offset + (last as u64) < section_start
+}
+
+fn table_entry(
+    entry: u32,
+    table_offset: u64,
+    prev_offset: Option<u64>,
+    overflow: &mut bool
+) -> Chunk
+{
+    let masked_offset = table_offset + ((entry & 0x7fffffff) as u64);
+    let full_offset = table_offset + entry as u64;
+
+    // EnCase 6 and later write segments larger than 2 GiB, where offsets
+    // past the 2 GiB mark need the top bit, so it no longer indicates
+    // compression. Chunk offsets increase, so a masked offset smaller than
+    // the previous one means we've crossed that mark.
+    if !*overflow && let Some(prev_offset) = prev_offset && masked_offset < prev_offset {
+        if full_offset >= prev_offset {
+            debug!("table offsets overflow at {full_offset}");
+            *overflow = true;
+        }
+        else {
+            warn!("table offset {masked_offset} precedes previous offset {prev_offset}");
+        }
+    }
 
-    Ok(
+    if *overflow {
         Chunk {
             segment: 0,
-            data_offset: table_offset + ((entry & 0x7fffffff) as u64),
+            data_offset: full_offset,
             end_offset: 0,
-            compressed: (entry & 0x80000000) > 0
+            compressed: false,
+            has_checksum: true,
+            pattern: None,
+            compression_unknown: true
         }
-    )
+    }
+    else {
+        let compressed = (entry & 0x80000000) > 0;
+
+        Chunk {
+            segment: 0,
+            data_offset: masked_offset,
+            end_offset: 0,
+            compressed,
+            // uncompressed EWF1 chunks always have a checksum
+            has_checksum: !compressed,
+            pattern: None,
+            compression_unknown: false
+        }
+    }
 }
 
 pub fn read_table(
     io: &BytesReader,
-    _size: u64,
+    section_start: u64,
     ignore_checksums: bool,
 ) -> Result<Vec<Chunk>, LibError> {
     let table_section = EwfTableHeader::read_into::<_, EwfTableHeader>(io, None, None)
@@ -193,10 +494,29 @@ pub fn read_table(
     let table_offset = *table_section.table_base_offset();
     let mut chunks: Vec<Chunk> = Vec::with_capacity(entry_count);
 
-    chunks.push(read_table_entry(io, table_offset)?);
+    let entries = (0..entry_count)
+        .map(|_| io.read_u4le().map_err(IoError::Read))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut overflow = table_starts_overflowed(
+        &entries,
+        table_offset,
+        section_start
+    );
+
+    if overflow {
+        debug!("table offsets overflow from the first, {}", entries[0]);
+    }
+
+    chunks.push(table_entry(entries[0], table_offset, None, &mut overflow));
 
     for i in 1..entry_count {
-        let ch = read_table_entry(io, table_offset)?;
+        let ch = table_entry(
+            entries[i],
+            table_offset,
+            Some(chunks[i - 1].data_offset),
+            &mut overflow
+        );
         chunks[i - 1].end_offset = ch.data_offset;
         chunks.push(ch);
     }
@@ -227,7 +547,9 @@ pub struct VolumeSection {
     pub chunk_count: u32,
     pub sectors_per_chunk: u32,
     pub bytes_per_sector: u32,
-    pub total_sector_count: u64
+    pub total_sector_count: u64,
+    // SMART volume sections have no media information
+    pub media_info: Option<MediaInfo>
 }
 
 impl VolumeSection {
@@ -247,11 +569,27 @@ impl VolumeSection {
                 )?;
             }
 
+            let media_info = MediaInfo {
+                media_type: Some((i64::from(&*vol_section.media_type()) as u8).into()),
+                media_flags: MediaFlags(i64::from(&*vol_section.media_flags()) as u8),
+                chs_cylinders: *vol_section.chs_cylinders(),
+                chs_heads: *vol_section.chs_heads(),
+                chs_sectors: *vol_section.chs_sectors(),
+                compression_level: (i64::from(&*vol_section.compression_level()) as u8).into(),
+                error_granularity: *vol_section.error_granularity(),
+                palm_volume_start_sector: *vol_section.palm_volume_start_sector(),
+                smart_logs_start_sector: *vol_section.smart_logs_start_sector(),
+                set_identifier: vol_section.set_identifier()[..]
+                    .try_into()
+                    .expect("set identifier must deserialize to 16 bytes")
+            };
+
             let vs = VolumeSection {
                 chunk_count: *vol_section.number_of_chunks(),
                 sectors_per_chunk: *vol_section.sectors_per_chunk(),
                 bytes_per_sector: *vol_section.bytes_per_sector(),
                 total_sector_count: *vol_section.number_of_sectors(),
+                media_info: Some(media_info)
             };
             Ok(vs)
         }
@@ -273,6 +611,7 @@ impl VolumeSection {
                 sectors_per_chunk: *vol_section.sectors_per_chunk(),
                 bytes_per_sector: *vol_section.bytes_per_sector(),
                 total_sector_count: *vol_section.number_of_sectors() as u64,
+                media_info: None
             };
             Ok(vs)
         }
@@ -343,3 +682,163 @@ impl Iterator for SectionIterator<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn table(base: u64, entries: &[u32]) -> BytesReader {
+        let mut data = vec![];
+        data.extend((entries.len() as u32).to_le_bytes());
+        data.extend([0; 4]);
+        data.extend(base.to_le_bytes());
+        data.extend([0; 8]);
+        for e in entries {
+            data.extend(e.to_le_bytes());
+        }
+        BytesReader::from(data)
+    }
+
+    #[test]
+    fn read_table_no_overflow() {
+        let chunks = read_table(&table(100, &[0, 0x80000010, 0x20]), 200, true).unwrap();
+
+        assert_eq!(
+            chunks.iter().map(|c| (c.data_offset, c.compressed)).collect::<Vec<_>>(),
+            [(100, false), (116, true), (132, false)]
+        );
+        assert!(chunks.iter().all(|c| !c.compression_unknown));
+    }
+
+    #[test]
+    fn read_table_overflow() {
+        // the third offset is past 2 GiB, so its top bit is part of the
+        // offset and not a compression flag
+        let entries = [0x7fff0000, 0xffff8000, 0x80001000, 0x80002000];
+        let chunks = read_table(&table(0, &entries), 0x80003000, true).unwrap();
+
+        assert_eq!(
+            chunks.iter().map(|c| c.data_offset).collect::<Vec<_>>(),
+            [0x7fff0000, 0x7fff8000, 0x80001000, 0x80002000]
+        );
+        assert!(!chunks[0].compression_unknown);
+        assert!(chunks[1].compressed && !chunks[1].compression_unknown);
+        assert!(chunks[2].compression_unknown && chunks[3].compression_unknown);
+        assert_eq!(chunks[1].end_offset, 0x80001000);
+    }
+
+    #[test]
+    fn read_table_starts_overflowed() {
+        // the data of the table is all past 2 GiB from its base, up to the
+        // table section
+        let entries = [0x80001000, 0x80009000, 0x80011000];
+        let chunks = read_table(&table(0x100, &entries), 0x80019100, true).unwrap();
+
+        assert_eq!(
+            chunks.iter().map(|c| c.data_offset).collect::<Vec<_>>(),
+            [0x80001100, 0x80009100, 0x80011100]
+        );
+        assert!(chunks.iter().all(|c| c.compression_unknown));
+    }
+
+    #[test]
+    fn read_table_all_compressed() {
+        // the same entries, but with the table just past the masked offsets,
+        // so the top bits are compression flags
+        let entries = [0x80001000, 0x80009000, 0x80011000];
+        let chunks = read_table(&table(0x100, &entries), 0x19100, true).unwrap();
+
+        assert_eq!(
+            chunks.iter().map(|c| (c.data_offset, c.compressed)).collect::<Vec<_>>(),
+            [(0x1100, true), (0x9100, true), (0x11100, true)]
+        );
+        assert!(chunks.iter().all(|c| !c.compression_unknown));
+    }
+
+    fn error2(entries: &[(u32, u32)]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend((entries.len() as u32).to_le_bytes());
+        data.extend([0; 512]);
+        data.extend(adler32::RollingAdler32::from_buffer(&data).hash().to_le_bytes());
+
+        let mut e = vec![];
+        for (first, count) in entries {
+            e.extend(first.to_le_bytes());
+            e.extend(count.to_le_bytes());
+        }
+        data.extend(&e);
+        data.extend(adler32::RollingAdler32::from_buffer(&e).hash().to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn read_error2_section_ok() {
+        let errors = read_error2_section(
+            &BytesReader::from(error2(&[(10, 2), (0, 1), (0xffffff00, 0x100)])),
+            false
+        ).unwrap();
+
+        assert_eq!(errors, [(10, 2), (0, 1), (0xffffff00, 0x100)]);
+    }
+
+    #[test]
+    fn read_error2_section_bad_header_checksum() {
+        let mut data = error2(&[(10, 2), (0, 1)]);
+        // the entry count
+        data[0] ^= 0x01;
+
+        assert!(matches!(
+            read_error2_section(&BytesReader::from(data), false),
+            Err(LibError::BadChecksum(..))
+        ));
+    }
+
+    #[test]
+    fn read_error2_section_bad_entries_checksum() {
+        let mut data = error2(&[(10, 2), (0, 1)]);
+        // the first entry's sector count
+        data[520 + 4] ^= 0x01;
+
+        assert!(matches!(
+            read_error2_section(&BytesReader::from(data.clone()), false),
+            Err(LibError::BadChecksum(..))
+        ));
+
+        // the checksum may be ignored
+        assert_eq!(
+            read_error2_section(&BytesReader::from(data), true).unwrap(),
+            [(10, 3), (0, 1)]
+        );
+    }
+
+    // a section with the given zlib-compressed data, alone in a segment
+    fn compressed_section(section_type: &str, data: &[u8]) -> BytesReader {
+        let mut enc = flate2::write::ZlibEncoder::new(
+            vec![],
+            flate2::Compression::default()
+        );
+        std::io::Write::write_all(&mut enc, data).unwrap();
+        let data = enc.finish().unwrap();
+
+        let mut sec = vec![0; 16];
+        sec[..section_type.len()].copy_from_slice(section_type.as_bytes());
+        // next offset is its own, as the last section; then size
+        sec.extend(0_u64.to_le_bytes());
+        sec.extend((0x4c + data.len() as u64).to_le_bytes());
+        sec.extend([0; 44]);
+        sec.extend(data);
+        BytesReader::from(sec)
+    }
+
+    #[test]
+    fn read_section_malformed_xhash() {
+        let data = b"<?xml version=\"1.0\"?>\n<xhash>\n\t<md5>00</md5>\n";
+
+        assert!(read_section(&compressed_section("xhash", data), false).is_err());
+
+        assert!(matches!(
+            read_section(&compressed_section("xhash", data), true),
+            Ok((_, Section::Other))
+        ));
+    }
+}
@@ -0,0 +1,736 @@
+use kaitai::{BytesReader, KStream, KStruct};
+
+use crate::case_metadata::{CaseMetadata, decode_utf16le, main_category};
+use crate::error::{IoError, LibError};
+use crate::media_info::MediaInfo;
+use crate::generated::{
+    ewf_error_header_v2::EwfErrorHeaderV2,
+    ewf_section_descriptor_v2::EwfSectionDescriptorV2,
+    ewf_session_header_v2::EwfSessionHeaderV2,
+    ewf_table_header_v2::EwfTableHeaderV2
+};
+use crate::sec_read::{
+    Chunk,
+    Section,
+    VolumeSection,
+    checksum_ok,
+    checksum_reader,
+    read_compressed_section
+};
+
+// EWF2 section types
+const DEVICE_INFORMATION: u32 = 0x01;
+const CASE_DATA: u32 = 0x02;
+const SECTOR_TABLE: u32 = 0x04;
+const ERROR_TABLE: u32 = 0x05;
+const SESSION_TABLE: u32 = 0x06;
+const MD5_HASH: u32 = 0x08;
+const SHA1_HASH: u32 = 0x09;
+const DONE: u32 = 0x0f;
+const SINGLE_FILES_DATA: u32 = 0x20;
+
+// section data flags
+const SECTION_ENCRYPTED: u32 = 0x02;
+
+// sector table entry flags
+const CHUNK_COMPRESSED: u32 = 0x01;
+const CHUNK_HAS_CHECKSUM: u32 = 0x02;
+const CHUNK_PATTERN_FILL: u32 = 0x04;
+
+const DESCRIPTOR_SIZE: usize = 64;
+
+#[derive(Debug)]
+pub struct DeviceInformation {
+    pub sector_count: u64,
+    pub bytes_per_sector: u32,
+    pub values: Vec<(String, String)>
+}
+
+#[derive(Debug)]
+pub struct CaseData {
+    pub sectors_per_chunk: u32,
+    pub metadata: CaseMetadata
+}
+
+// EWF2 splits what EWF1 keeps in the volume section across the device
+// information and case data sections
+pub fn volume_section(
+    dev: &DeviceInformation,
+    case: &CaseData
+) -> Result<VolumeSection, LibError>
+{
+    let chunk_count = dev.sector_count
+        .div_ceil(case.sectors_per_chunk as u64);
+
+    Ok(
+        VolumeSection {
+            chunk_count: u32::try_from(chunk_count)
+                .map_err(|_| LibError::MalformedSection(
+                    "device information",
+                    format!("{chunk_count} chunks is too many")
+                ))?,
+            sectors_per_chunk: case.sectors_per_chunk,
+            bytes_per_sector: dev.bytes_per_sector,
+            total_sector_count: dev.sector_count,
+            media_info: Some(MediaInfo::from_device_information(&dev.values))
+        }
+    )
+}
+
+fn find_value<T: std::str::FromStr>(
+    section_type: &'static str,
+    values: &[(String, String)],
+    key: &str
+) -> Result<T, LibError>
+{
+    values.iter()
+        .find(|(k, _)| k == key)
+        .and_then(|(_, v)| v.trim().parse().ok())
+        .ok_or_else(|| LibError::MalformedSection(
+            section_type,
+            format!("missing or bad value for {key}")
+        ))
+}
+
+fn read_device_information(
+    io: &BytesReader,
+    size: u64
+) -> Result<DeviceInformation, LibError> {
+    let text = decode_utf16le(
+        &read_compressed_section("device information", io, size)?
+    );
+
+    let values = main_category("device information", &text)?;
+
+    Ok(
+        DeviceInformation {
+            sector_count: find_value("device information", &values, "ts")?,
+            bytes_per_sector: find_value("device information", &values, "bp")?,
+            values
+        }
+    )
+}
+
+fn read_case_data(
+    io: &BytesReader,
+    size: u64
+) -> Result<CaseData, LibError> {
+    let text = decode_utf16le(
+        &read_compressed_section("case data", io, size)?
+    );
+
+    let values = main_category("case data", &text)?;
+
+    let sectors_per_chunk = find_value("case data", &values, "sb")?;
+    if sectors_per_chunk == 0 {
+        return Err(LibError::MalformedSection(
+            "case data",
+            "sectors per chunk is zero".into()
+        ));
+    }
+
+    Ok(
+        CaseData {
+            sectors_per_chunk,
+            metadata: CaseMetadata::from_case_data(&values)
+        }
+    )
+}
+
+fn read_hash<const N: usize>(
+    section_type: &str,
+    io: &BytesReader,
+    ignore_checksums: bool
+) -> Result<[u8; N], LibError> {
+    let hash_io = Clone::clone(io);
+
+    let hash = io.read_bytes(N).map_err(IoError::Read)?;
+
+    if !ignore_checksums {
+        let crc_stored = io.read_u4le().map_err(IoError::Read)?;
+        let crc = checksum_reader(&hash_io, N)?;
+
+        if crc != crc_stored {
+            return Err(LibError::BadChecksum(section_type.into(), crc, crc_stored));
+        }
+    }
+
+    Ok(
+        hash[..]
+            .try_into()
+            .expect("hash must deserialize to N bytes")
+    )
+}
+
+fn read_table_entry(io: &BytesReader) -> Result<Chunk, LibError> {
+    let offset = io.read_u8le().map_err(IoError::Read)?;
+    let size = io.read_u4le().map_err(IoError::Read)?;
+    let flags = io.read_u4le().map_err(IoError::Read)?;
+
+    Ok(
+        if flags & CHUNK_PATTERN_FILL != 0 {
+            // the offset is the fill pattern; there is no chunk data
+            Chunk {
+                segment: 0,
+                data_offset: 0,
+                end_offset: 0,
+                compressed: false,
+                has_checksum: false,
+                pattern: Some(offset.to_le_bytes()),
+                compression_unknown: false
+            }
+        }
+        else {
+            Chunk {
+                segment: 0,
+                data_offset: offset,
+                end_offset: offset + size as u64,
+                compressed: flags & CHUNK_COMPRESSED != 0,
+                has_checksum: flags & CHUNK_HAS_CHECKSUM != 0,
+                pattern: None,
+                compression_unknown: false
+            }
+        }
+    )
+}
+
+fn read_table(
+    io: &BytesReader,
+    ignore_checksums: bool
+) -> Result<Vec<Chunk>, LibError> {
+    let io_header = Clone::clone(io);
+
+    let table_header = EwfTableHeaderV2::read_into::<_, EwfTableHeaderV2>(io, None, None)
+        .map_err(|e| LibError::DeserializationFailed("EwfTableHeaderV2", e))?;
+
+    if !ignore_checksums {
+        // the checksum covers the first chunk number and entry count
+        let crc_stored = *table_header.checksum();
+        let crc = checksum_reader(&io_header, 16)?;
+
+        if crc != crc_stored {
+            return Err(LibError::BadChecksum("Sector table".into(), crc, crc_stored));
+        }
+    }
+
+    let entry_count = *table_header.entry_count() as usize;
+
+    let io_entries = Clone::clone(io);
+
+    let chunks = (0..entry_count)
+        .map(|_| read_table_entry(io))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !ignore_checksums && entry_count > 0 {
+        // table footer
+        let crc_stored = io.read_u4le().map_err(IoError::Read)?;
+        let crc = checksum_reader(&io_entries, entry_count * 16)?;
+
+        if crc != crc_stored {
+            return Err(LibError::BadChecksum(
+                "Sector table entries".into(),
+                crc,
+                crc_stored
+            ));
+        }
+    }
+
+    Ok(chunks)
+}
+
+fn read_error_table(
+    io: &BytesReader,
+    ignore_checksums: bool
+) -> Result<Vec<(u64, u64)>, LibError> {
+    let io_header = Clone::clone(io);
+
+    let error_header = EwfErrorHeaderV2::read_into::<_, EwfErrorHeaderV2>(io, None, None)
+        .map_err(|e| LibError::DeserializationFailed("EwfErrorHeaderV2", e))?;
+
+    if !ignore_checksums {
+        // the checksum covers the entry count and the padding after it
+        let crc_stored = *error_header.checksum();
+        let crc = checksum_reader(&io_header, 16)?;
+
+        if crc != crc_stored {
+            return Err(LibError::BadChecksum("Error table".into(), crc, crc_stored));
+        }
+    }
+
+    let entry_count = *error_header.number_of_entries() as usize;
+
+    let io_entries = Clone::clone(io);
+
+    let errors = (0..entry_count)
+        .map(|_| {
+            let first_sector = io.read_u8le().map_err(IoError::Read)?;
+            let sector_count = io.read_u4le().map_err(IoError::Read)? as u64;
+            // padding
+            io.read_bytes(4).map_err(IoError::Read)?;
+            Ok((first_sector, sector_count))
+        })
+        .collect::<Result<Vec<_>, LibError>>()?;
+
+    if !ignore_checksums && entry_count > 0 {
+        // error table footer
+        let crc_stored = io.read_u4le().map_err(IoError::Read)?;
+        let crc = checksum_reader(&io_entries, entry_count * 16)?;
+
+        if crc != crc_stored {
+            return Err(LibError::BadChecksum(
+                "Error table entries".into(),
+                crc,
+                crc_stored
+            ));
+        }
+    }
+
+    Ok(errors)
+}
+
+fn read_session_table(
+    io: &BytesReader,
+    ignore_checksums: bool
+) -> Result<Vec<(u64, u32)>, LibError> {
+    let io_header = Clone::clone(io);
+
+    let session_header = EwfSessionHeaderV2::read_into::<_, EwfSessionHeaderV2>(io, None, None)
+        .map_err(|e| LibError::DeserializationFailed("EwfSessionHeaderV2", e))?;
+
+    if !ignore_checksums {
+        // the checksum covers the entry count and the padding after it
+        let crc_stored = *session_header.checksum();
+        let crc = checksum_reader(&io_header, 16)?;
+
+        if crc != crc_stored {
+            return Err(LibError::BadChecksum("Session table".into(), crc, crc_stored));
+        }
+    }
+
+    let entry_count = *session_header.number_of_entries() as usize;
+
+    let io_entries = Clone::clone(io);
+
+    let sessions = (0..entry_count)
+        .map(|_| {
+            let start_sector = io.read_u8le().map_err(IoError::Read)?;
+            let flags = io.read_u4le().map_err(IoError::Read)?;
+            // padding
+            io.read_bytes(20).map_err(IoError::Read)?;
+            Ok((start_sector, flags))
+        })
+        .collect::<Result<Vec<_>, LibError>>()?;
+
+    if !ignore_checksums && entry_count > 0 {
+        // session table footer
+        let crc_stored = io.read_u4le().map_err(IoError::Read)?;
+        let crc = checksum_reader(&io_entries, entry_count * 32)?;
+
+        if crc != crc_stored {
+            return Err(LibError::BadChecksum(
+                "Session table entries".into(),
+                crc,
+                crc_stored
+            ));
+        }
+    }
+
+    Ok(sessions)
+}
+
+#[derive(Debug)]
+struct SectionDescriptor {
+    type_num: u32,
+    data_flags: u32,
+    data_offset: usize,
+    data_size: u64
+}
+
+// In EWF2, each section descriptor follows the section data it describes,
+// and points back to the previous section descriptor.
+fn read_descriptor(
+    io: &BytesReader,
+    offset: usize,
+    ignore_checksums: bool
+) -> Result<(SectionDescriptor, usize), LibError> {
+    io.seek(offset).map_err(|e| IoError::Seek(offset, e))?;
+
+    let sd = EwfSectionDescriptorV2::read_into::<_, EwfSectionDescriptorV2>(io, None, None)
+        .map_err(|e| LibError::DeserializationFailed("EwfSectionDescriptorV2", e))?;
+
+    if !ignore_checksums {
+        checksum_ok(
+            "Section descriptor",
+            io,
+            &sd._io(),
+            *sd.checksum()
+        )?;
+    }
+
+    // the data size includes the padding which follows the data
+    let data_size = *sd.data_size();
+    let padding_size = *sd.padding_size() as u64;
+
+    if data_size > offset as u64 || padding_size > data_size {
+        return Err(LibError::MalformedSection(
+            "section descriptor",
+            format!("bad data size {data_size} at {offset}")
+        ));
+    }
+
+    Ok((
+        SectionDescriptor {
+            type_num: *sd.type_num(),
+            data_flags: *sd.data_flags(),
+            data_offset: offset - data_size as usize,
+            data_size: data_size - padding_size
+        },
+        *sd.previous_offset() as usize
+    ))
+}
+
+fn read_section(
+    io: &BytesReader,
+    sd: &SectionDescriptor,
+    ignore_checksums: bool
+) -> Result<Section, LibError> {
+    if sd.data_flags & SECTION_ENCRYPTED != 0 {
+        return Err(LibError::EncryptedSection(sd.type_num));
+    }
+
+    io.seek(sd.data_offset).map_err(|e| IoError::Seek(sd.data_offset, e))?;
+
+    Ok(
+        match sd.type_num {
+            DEVICE_INFORMATION => Section::DeviceInformation(
+                read_device_information(io, sd.data_size)?
+            ),
+            CASE_DATA => Section::CaseData(read_case_data(io, sd.data_size)?),
+            SECTOR_TABLE => Section::SectorTable(read_table(io, ignore_checksums)?),
+            ERROR_TABLE => Section::Error(read_error_table(io, ignore_checksums)?),
+            SESSION_TABLE => Section::Session(read_session_table(io, ignore_checksums)?),
+            MD5_HASH => Section::Hash(read_hash("MD5 hash section", io, ignore_checksums)?),
+            SHA1_HASH => Section::Sha1(read_hash("SHA1 hash section", io, ignore_checksums)?),
+            SINGLE_FILES_DATA => Section::LTree(decode_utf16le(
+                &read_compressed_section("single files data", io, sd.data_size)?
+            )),
+            DONE => Section::Done,
+            _ => Section::Other
+        }
+    )
+}
+
+pub struct SectionIteratorV2<'a> {
+    io: &'a BytesReader,
+    descriptors: std::vec::IntoIter<SectionDescriptor>,
+    ignore_checksums: bool
+}
+
+impl<'a> SectionIteratorV2<'a> {
+    pub fn new(
+        io: &'a BytesReader,
+        ignore_checksums: bool
+    ) -> Result<Self, LibError> {
+        // the last section descriptor is at the end of the segment
+        let mut offset = io.size().checked_sub(DESCRIPTOR_SIZE)
+            .ok_or_else(|| LibError::MalformedSection(
+                "section descriptor",
+                "segment is too short".into()
+            ))?;
+
+        // walk back to the first section descriptor
+        let mut descriptors = vec![];
+
+        loop {
+            let (sd, prev_offset) = read_descriptor(io, offset, ignore_checksums)?;
+            descriptors.push(sd);
+
+            if prev_offset == 0 {
+                break;
+            }
+            else if prev_offset >= offset {
+                return Err(LibError::MalformedSection(
+                    "section descriptor",
+                    format!("previous offset {prev_offset} does not precede {offset}")
+                ));
+            }
+
+            offset = prev_offset;
+        }
+
+        // yield the sections in the order they appear in the segment
+        descriptors.reverse();
+
+        Ok(
+            Self {
+                io,
+                descriptors: descriptors.into_iter(),
+                ignore_checksums
+            }
+        )
+    }
+}
+
+impl Iterator for SectionIteratorV2<'_> {
+    type Item = Result<Section, LibError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.descriptors.next()
+            .map(|sd| read_section(self.io, &sd, self.ignore_checksums))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{
+        segment::CompressionMethod,
+        test_helper::{Ewf2Chunk, ewf2_segment}
+    };
+
+    fn table(entries: &[(u64, u32, u32)]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend(0_u64.to_le_bytes());
+        data.extend((entries.len() as u32).to_le_bytes());
+        data.extend([0; 4]);
+        data.extend(adler32::RollingAdler32::from_buffer(&data).hash().to_le_bytes());
+        data.extend([0; 12]);
+
+        let mut e = vec![];
+        for (offset, size, flags) in entries {
+            e.extend(offset.to_le_bytes());
+            e.extend(size.to_le_bytes());
+            e.extend(flags.to_le_bytes());
+        }
+        data.extend(&e);
+        data.extend(adler32::RollingAdler32::from_buffer(&e).hash().to_le_bytes());
+        data
+    }
+
+    const ENTRIES: [(u64, u32, u32); 3] = [
+        // past 4 GiB
+        (0x1_0000_1000, 0x200, CHUNK_COMPRESSED),
+        (0x1_0000_1200, 0x804, CHUNK_HAS_CHECKSUM),
+        (0x0101010101010101, 0, CHUNK_PATTERN_FILL)
+    ];
+
+    #[test]
+    fn read_table_ok() {
+        let chunks = read_table(&BytesReader::from(table(&ENTRIES)), false).unwrap();
+
+        assert_eq!(
+            chunks.iter()
+                .map(|c| (c.data_offset, c.end_offset, c.compressed, c.has_checksum, c.pattern))
+                .collect::<Vec<_>>(),
+            [
+                (0x1_0000_1000, 0x1_0000_1200, true, false, None),
+                (0x1_0000_1200, 0x1_0000_1a04, false, true, None),
+                (0, 0, false, false, Some([1; 8]))
+            ]
+        );
+    }
+
+    #[test]
+    fn read_table_bad_header_checksum() {
+        let mut data = table(&ENTRIES);
+        // the entry count
+        data[8] ^= 0x01;
+
+        assert!(matches!(
+            read_table(&BytesReader::from(data), false),
+            Err(LibError::BadChecksum(..))
+        ));
+    }
+
+    #[test]
+    fn read_table_bad_entries_checksum() {
+        let mut data = table(&ENTRIES);
+        // the first entry's flags
+        data[32 + 12] ^= 0x02;
+
+        assert!(matches!(
+            read_table(&BytesReader::from(data.clone()), false),
+            Err(LibError::BadChecksum(..))
+        ));
+
+        // the checksum may be ignored
+        let chunks = read_table(&BytesReader::from(data), true).unwrap();
+        assert!(chunks[0].compressed && chunks[0].has_checksum);
+    }
+
+    fn error_table(entries: &[(u64, u32)]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend((entries.len() as u32).to_le_bytes());
+        data.extend([0; 12]);
+        data.extend(adler32::RollingAdler32::from_buffer(&data).hash().to_le_bytes());
+        data.extend([0; 12]);
+
+        let mut e = vec![];
+        for (first, count) in entries {
+            e.extend(first.to_le_bytes());
+            e.extend(count.to_le_bytes());
+            e.extend([0; 4]);
+        }
+        data.extend(&e);
+        data.extend(adler32::RollingAdler32::from_buffer(&e).hash().to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn read_error_table_ok() {
+        let errors = read_error_table(
+            &BytesReader::from(error_table(&[(10, 2), (1 << 40, 0x100)])),
+            false
+        ).unwrap();
+
+        assert_eq!(errors, [(10, 2), (1 << 40, 0x100)]);
+    }
+
+    #[test]
+    fn read_error_table_bad_header_checksum() {
+        let mut data = error_table(&[(10, 2), (1 << 40, 0x100)]);
+        // the entry count
+        data[0] ^= 0x01;
+
+        assert!(matches!(
+            read_error_table(&BytesReader::from(data), false),
+            Err(LibError::BadChecksum(..))
+        ));
+    }
+
+    #[test]
+    fn read_error_table_bad_entries_checksum() {
+        let mut data = error_table(&[(10, 2), (1 << 40, 0x100)]);
+        // the first entry's sector count
+        data[32 + 8] ^= 0x01;
+
+        assert!(matches!(
+            read_error_table(&BytesReader::from(data.clone()), false),
+            Err(LibError::BadChecksum(..))
+        ));
+
+        // the checksum may be ignored
+        assert_eq!(
+            read_error_table(&BytesReader::from(data), true).unwrap(),
+            [(10, 3), (1 << 40, 0x100)]
+        );
+    }
+
+    #[test]
+    fn section_iterator_in_segment_order() {
+        let data = vec![0; 2048];
+        let seg = ewf2_segment(
+            &data,
+            2,
+            CompressionMethod::Deflate,
+            &[Ewf2Chunk::Compressed, Ewf2Chunk::Checksummed],
+            &[(1, 2)]
+        );
+
+        let io = BytesReader::from(seg);
+        let sections = SectionIteratorV2::new(&io, false)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert!(matches!(
+            &sections[..],
+            [
+                Section::DeviceInformation(DeviceInformation { sector_count: 4, bytes_per_sector: 512, .. }),
+                Section::CaseData(CaseData { sectors_per_chunk: 2, .. }),
+                Section::Other,
+                Section::SectorTable(t),
+                Section::Error(e),
+                Section::Hash(_),
+                Section::Done
+            ] if t.len() == 2 && e[..] == [(1, 2)]
+        ));
+    }
+
+    #[test]
+    fn section_iterator_bad_previous_offset() {
+        let mut seg = ewf2_segment(
+            &[0; 512],
+            1,
+            CompressionMethod::Deflate,
+            &[Ewf2Chunk::Compressed],
+            &[]
+        );
+
+        // point the last descriptor at itself, ignoring its checksum
+        let len = seg.len();
+        let last = (len as u64 - 64).to_le_bytes();
+        seg[len - 56..len - 48].copy_from_slice(&last);
+
+        assert!(matches!(
+            SectionIteratorV2::new(&BytesReader::from(seg), true),
+            Err(LibError::MalformedSection(..))
+        ));
+    }
+
+    #[test]
+    fn volume_section_rounds_up() {
+        let dev = DeviceInformation {
+            sector_count: 100,
+            bytes_per_sector: 512,
+            values: vec![]
+        };
+
+        let case = CaseData {
+            sectors_per_chunk: 64,
+            metadata: CaseMetadata::default()
+        };
+
+        let vs = volume_section(&dev, &case).unwrap();
+        assert_eq!(vs.chunk_count, 2);
+        assert_eq!(vs.total_sector_count, 100);
+    }
+
+    #[test]
+    fn volume_section_too_many_chunks() {
+        let dev = DeviceInformation {
+            sector_count: 1 << 40,
+            bytes_per_sector: 512,
+            values: vec![]
+        };
+
+        let case = CaseData {
+            sectors_per_chunk: 64,
+            metadata: CaseMetadata::default()
+        };
+
+        assert!(matches!(
+            volume_section(&dev, &case),
+            Err(LibError::MalformedSection(..))
+        ));
+    }
+
+    #[test]
+    fn read_table_entry_pattern_fill() {
+        let data = [
+            0x0102030405060708_u64.to_le_bytes().as_slice(),
+            &0_u32.to_le_bytes(),
+            &CHUNK_PATTERN_FILL.to_le_bytes()
+        ].concat();
+
+        let chunk = read_table_entry(&BytesReader::from(data)).unwrap();
+        assert_eq!(chunk.pattern, Some([8, 7, 6, 5, 4, 3, 2, 1]));
+        assert!(!chunk.has_checksum);
+    }
+
+    #[test]
+    fn read_table_entry_compressed() {
+        let data = [
+            0x1000_u64.to_le_bytes().as_slice(),
+            &0x200_u32.to_le_bytes(),
+            &CHUNK_COMPRESSED.to_le_bytes()
+        ].concat();
+
+        let chunk = read_table_entry(&BytesReader::from(data)).unwrap();
+        assert_eq!(chunk.data_offset, 0x1000);
+        assert_eq!(chunk.end_offset, 0x1200);
+        assert!(chunk.compressed);
+        assert_eq!(chunk.pattern, None);
+    }
+}
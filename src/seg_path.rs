@@ -1,8 +1,21 @@
 use itertools::iproduct;
+use std::collections::HashMap;
 use tracing::debug;
 
+// EWF2 segment extensions have an x after the first character, e.g., Ex01,
+// but otherwise follow the same sequence as EWF1 extensions
+fn strip_ewf2_marker(ext: &str) -> (String, bool) {
+    let mut chars = ext.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), Some('x' | 'X')) if ext.chars().count() == 4 =>
+            (format!("{c}{}", chars.as_str()), true),
+        _ => (ext.into(), false)
+    }
+}
+
 #[allow(clippy::manual_is_ascii_check)]
 fn valid_segment_ext(ext: &str) -> bool {
+    let (ext, _) = strip_ewf2_marker(ext);
     let ext = ext.to_ascii_uppercase();
     let mut ext = ext.chars();
 
@@ -34,14 +47,19 @@ fn valid_proto_segment_ext(ext: &str) -> bool {
     )
 }
 
-fn segment_ext_iter(start: char) -> impl Iterator<Item = String> {
+fn segment_ext_iter(
+    start: char,
+    ewf2: bool
+) -> impl Iterator<Item = String> {
+    let x = if ewf2 { "x" } else { "" };
+
     // x01 to x99
     (1..=99)
-        .map(move |n| format!("{}{:02}", start, n))
+        .map(move |n| format!("{}{}{:02}", start, x, n))
         // xAA - ZZZ
         .chain(
             iproduct!(start..='Z', 'A'..='Z', 'A'..='Z')
-                .map(|t| format!("{}{}{}", t.0, t.1, t.2))
+                .map(move |t| format!("{}{}{}{}", t.0, x, t.1, t.2))
         )
 }
 
@@ -61,20 +79,26 @@ fn validate_proto_extension<T: AsRef<str>>(
         .ok_or(UnrecognizedExtension(path.as_ref().into()))
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum SegmentPathError {
+    #[error("{0}")]
+    UnrecognizedExtension(#[from] UnrecognizedExtension),
+    #[error("Checking for segment {0} failed: {1}")]
+    CheckFailed(String, #[source] std::io::Error)
+}
+
 pub trait ExistsChecker {
-    fn exists<T: AsRef<str>>(&mut self, path: T) -> bool;
+    // Errors other than the path not existing are returned, so that
+    // segments aren't silently dropped
+    fn exists<T: AsRef<str>>(&mut self, path: T) -> Result<bool, std::io::Error>;
 }
 
-pub fn validated_segment_paths<T, C>(
-    example_segment_path: T,
-    mut checker: C
-) -> Result<impl IntoIterator<Item: AsRef<str>>, UnrecognizedExtension>
-where
-    T: AsRef<str>,
-    C: ExistsChecker
+// Returns the first character of the extension, whether the segments are
+// EWF2, and the path without the extension
+fn proto_path_parts(
+    proto_path: &str
+) -> Result<(char, bool, String), UnrecognizedExtension>
 {
-    let proto_path = example_segment_path.as_ref();
-
     // Get the extension from the prototype path
     let proto_ext = validate_proto_extension(proto_path)?;
 
@@ -82,32 +106,94 @@ where
     let ext_start = proto_ext.chars().next()
         .ok_or(UnrecognizedExtension(proto_path.into()))?;
 
+    let (_, ewf2) = strip_ewf2_marker(&proto_ext);
+
     let base_path = proto_path
         .rsplit_once('.')
         .map(|(base, _)| base.to_owned())
         .ok_or(UnrecognizedExtension(proto_path.into()))?;
 
+    Ok((ext_start, ewf2, base_path))
+}
+
+pub fn validated_segment_paths<T, C>(
+    example_segment_path: T,
+    mut checker: C
+) -> Result<Vec<String>, SegmentPathError>
+where
+    T: AsRef<str>,
+    C: ExistsChecker
+{
+    let (ext_start, ewf2, base_path) = proto_path_parts(
+        example_segment_path.as_ref()
+    )?;
+
+    let mut exists = |p: &String| {
+        debug!("checking {p}");
+        checker.exists(p)
+            .map_err(|e| SegmentPathError::CheckFailed(p.clone(), e))
+    };
+
+    let mut segment_paths = vec![];
+
+    for ext in segment_ext_iter(ext_start, ewf2) {
+        // Hilariously, EnCase will create .E02 etc. if you start with
+        // .e01, so the extensions can actually differ in case through
+        // the sequence...
+        let seg_path_uc = format!("{base_path}.{ext}");
+        if exists(&seg_path_uc)? {
+            segment_paths.push(seg_path_uc);
+            continue;
+        }
+
+        let seg_path_lc = format!("{base_path}.{}", &ext.to_ascii_lowercase());
+        if exists(&seg_path_lc)? {
+            segment_paths.push(seg_path_lc);
+            continue;
+        }
+
+        break;
+    }
+
+    Ok(segment_paths)
+}
+
+// Picks the segment paths out of a listing of paths, e.g., the keys from
+// one S3 ListObjectsV2 request, in the order and up to the same gap as
+// validated_segment_paths would find them.
+pub fn listed_segment_paths<T, L>(
+    example_segment_path: T,
+    listing: L
+) -> Result<Vec<String>, UnrecognizedExtension>
+where
+    T: AsRef<str>,
+    L: IntoIterator<Item: AsRef<str>>
+{
+    let (ext_start, ewf2, base_path) = proto_path_parts(
+        example_segment_path.as_ref()
+    )?;
+
+    // index the listed segment paths by uppercase extension, preferring
+    // uppercase extensions when both cases are present
+    let mut by_ext = HashMap::new();
+    for p in listing {
+        let p = p.as_ref();
+        let Some(ext) = p.strip_prefix(base_path.as_str())
+            .and_then(|r| r.strip_prefix('.'))
+            .filter(|ext| valid_segment_ext(ext)) else {
+            continue;
+        };
+
+        let ext_uc = ext.to_ascii_uppercase();
+        if ext == ext_uc || !by_ext.contains_key(&ext_uc) {
+            by_ext.insert(ext_uc, p.to_string());
+        }
+    }
+
     Ok(
-        segment_ext_iter(ext_start)
-            .map_while(move |ext| {
-                // Hilariously, EnCase will create .E02 etc. if you start with
-                // .e01, so the extensions can actually differ in case through
-                // the sequence...
-                let seg_path_uc = format!("{base_path}.{ext}");
-                debug!("checking {seg_path_uc}");
-
-                if checker.exists(&seg_path_uc) {
-                    Some(seg_path_uc)
-                }
-                else {
-                    let seg_path_lc = format!(
-                        "{base_path}.{}",
-                        &ext.to_ascii_lowercase()
-                    );
-                    debug!("checking {seg_path_lc}");
-                    checker.exists(&seg_path_lc).then_some(seg_path_lc)
-                }
-            })
+        segment_ext_iter(ext_start, ewf2)
+            .map_while(|ext| by_ext.remove(&ext.to_ascii_uppercase()))
+            .collect()
     )
 }
 
@@ -127,7 +213,11 @@ mod test {
             "EZZ",
             "FAA",
             "YYZ",
-            "ZZZ"
+            "ZZZ",
+            "Ex01",
+            "Lx01",
+            "ExAA",
+            "ZxZZ"
         ];
 
         for ext in good {
@@ -147,6 +237,9 @@ mod test {
             "EA0",
             "AbC",
             "gtfo",
+            "Ex00",
+            "Ex1",
+            "Ex001",
             "💩"
         ];
 
@@ -165,7 +258,9 @@ mod test {
             "E99",
             "EAA",
             "EZZ",
-            "EZZ"
+            "EZZ",
+            "Ex01",
+            "Lx01"
         ];
 
         for ext in good {
@@ -243,7 +338,7 @@ mod test {
     #[test]
     fn segment_ext_iter_boundaries() {
         // check that a sample of extensions are in the expected positions
-        let mut i = segment_ext_iter('E');
+        let mut i = segment_ext_iter('E', false);
         assert_eq!(i.next(), Some("E01".into()));
         assert_eq!(i.next(), Some("E02".into()));
         let mut i = i.skip(96);
@@ -261,6 +356,91 @@ mod test {
         assert_eq!(i.next(), None);
     }
 
+    #[test]
+    fn segment_ext_iter_ewf2_boundaries() {
+        let mut i = segment_ext_iter('E', true);
+        assert_eq!(i.next(), Some("Ex01".into()));
+        let mut i = i.skip(97);
+        assert_eq!(i.next(), Some("Ex99".into()));
+        assert_eq!(i.next(), Some("ExAA".into()));
+        let mut i = i.skip(674);
+        assert_eq!(i.next(), Some("ExZZ".into()));
+        assert_eq!(i.next(), Some("FxAA".into()));
+    }
+
+    #[test]
+    fn listed_segment_paths_ok() {
+        let listing = [
+            "a/img.E03",
+            "a/img.txt",
+            "a/img.e02",
+            "a/img.E01",
+            "a/img.E01.bak",
+            "a/img.E05",
+            "a/other.E04"
+        ];
+
+        assert_eq!(
+            listed_segment_paths("a/img.E01", listing).unwrap(),
+            ["a/img.E01", "a/img.e02", "a/img.E03"]
+        );
+    }
+
+    #[test]
+    fn listed_segment_paths_prefers_uppercase() {
+        assert_eq!(
+            listed_segment_paths("a/img.e01", ["a/img.e01", "a/img.e02", "a/img.E02"]).unwrap(),
+            ["a/img.e01", "a/img.E02"]
+        );
+    }
+
+    #[test]
+    fn listed_segment_paths_ewf2() {
+        assert_eq!(
+            listed_segment_paths("s3://b/img.Ex01", ["s3://b/img.Ex01", "s3://b/img.E02", "s3://b/img.Ex02"]).unwrap(),
+            ["s3://b/img.Ex01", "s3://b/img.Ex02"]
+        );
+    }
+
+    #[test]
+    fn listed_segment_paths_bad_proto() {
+        assert_eq!(
+            listed_segment_paths("a/img.E00", ["a/img.E01"]).unwrap_err(),
+            UnrecognizedExtension("a/img.E00".into())
+        );
+    }
+
+    // Finds the listed paths, and fails on those named "fail"
+    struct ListChecker(&'static [&'static str]);
+
+    impl ExistsChecker for ListChecker {
+        fn exists<T: AsRef<str>>(&mut self, path: T) -> Result<bool, std::io::Error> {
+            match path.as_ref() {
+                p if p.starts_with("fail.") => Err(std::io::Error::other("oops")),
+                p => Ok(self.0.contains(&p))
+            }
+        }
+    }
+
+    #[test]
+    fn validated_segment_paths_ok() {
+        assert_eq!(
+            validated_segment_paths(
+                "a/img.e01",
+                ListChecker(&["a/img.e01", "a/img.E02", "a/img.e04"])
+            ).unwrap(),
+            ["a/img.e01", "a/img.E02"]
+        );
+    }
+
+    #[test]
+    fn validated_segment_paths_check_failed() {
+        assert!(matches!(
+            validated_segment_paths("fail.E01", ListChecker(&[])),
+            Err(SegmentPathError::CheckFailed(p, _)) if p == "fail.E01"
+        ));
+    }
+
 /*
     #[test]
     fn validate_segment_path_ok() {
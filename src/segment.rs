@@ -5,8 +5,8 @@ use crate::error::{IoError, LibError};
 use kaitai::{BytesReader, KStream, KStruct};
 use std::convert::TryFrom;
 
-#[derive(Debug)]
-enum CompressionMethod {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionMethod {
     None = 0,
     Deflate = 1,
     Bzip = 2,
@@ -27,10 +27,10 @@ impl TryFrom<u16> for CompressionMethod {
 
 #[derive(Debug)]
 pub struct SegmentFileHeader {
-    major_version: u8,
-    minor_version: u8,
-    compr_method: CompressionMethod,
-    segment_number: u16,
+    pub major_version: u8,
+    pub minor_version: u8,
+    pub compr_method: CompressionMethod,
+    pub segment_number: u16,
 }
 
 fn try_ewf_file_header_v1(
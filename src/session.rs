@@ -0,0 +1,164 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{
+    cursor::{E01Cursor, seek_position},
+    e01_reader::E01Reader
+};
+
+const SESSION_ENTRY_AUDIO_TRACK: u32 = 0x01;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub start_sector: u64,
+    pub sector_count: u64,
+    pub is_audio_track: bool
+}
+
+impl Session {
+    // Session entries give only the start sector; each session runs until
+    // the next one starts, and the last one to the end of the media.
+    pub(crate) fn from_entries(
+        entries: &[(u64, u32)],
+        total_sector_count: u64
+    ) -> Vec<Self>
+    {
+        let mut entries = entries.to_vec();
+        entries.sort_by_key(|(start_sector, _)| *start_sector);
+
+        let ends = entries.iter()
+            .skip(1)
+            .map(|(start_sector, _)| *start_sector)
+            .chain(std::iter::once(total_sector_count));
+
+        entries.iter()
+            .zip(ends)
+            .map(|(&(start_sector, flags), end)| {
+                let start_sector = start_sector.min(total_sector_count);
+                Session {
+                    start_sector,
+                    sector_count: end.min(total_sector_count) - start_sector,
+                    is_audio_track: flags & SESSION_ENTRY_AUDIO_TRACK != 0
+                }
+            })
+            .collect()
+    }
+}
+
+// A Read + Seek view of a range of sectors, e.g., a session or track.
+// Reads are buffered as by E01Cursor.
+pub struct SectorRangeReader<'a> {
+    cursor: E01Cursor<'a>,
+    beg: u64,
+    end: u64,
+    pos: u64
+}
+
+impl<'a> SectorRangeReader<'a> {
+    // The range is clamped to the image; it is an error if its offsets
+    // overflow.
+    pub fn new(
+        reader: &'a E01Reader,
+        start_sector: u64,
+        sector_count: u64
+    ) -> std::io::Result<Self>
+    {
+        let sector_size = reader.sector_size as u64;
+
+        let offset = |sector: Option<u64>| sector
+            .and_then(|s| s.checked_mul(sector_size))
+            .map(|off| off.min(reader.image_size))
+            .ok_or_else(|| std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("sectors {start_sector} + {sector_count} overflow")
+            ));
+
+        let beg = offset(Some(start_sector))?;
+        let end = offset(start_sector.checked_add(sector_count))?;
+
+        Ok(
+            Self {
+                cursor: E01Cursor::new(reader),
+                beg,
+                end,
+                pos: 0
+            }
+        )
+    }
+
+    pub fn len(&self) -> u64 {
+        self.end - self.beg
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.beg == self.end
+    }
+}
+
+impl Read for SectorRangeReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.len().saturating_sub(self.pos);
+        if remaining == 0 {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(remaining as usize);
+
+        self.cursor.seek(SeekFrom::Start(self.beg + self.pos))?;
+        let read = self.cursor.read(&mut buf[..len])?;
+
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for SectorRangeReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.pos = seek_position(pos, self.pos, self.len())?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_entries_ok() {
+        let entries = [(0, 0), (100, 0), (40, SESSION_ENTRY_AUDIO_TRACK)];
+
+        assert_eq!(
+            Session::from_entries(&entries, 150),
+            vec![
+                Session {
+                    start_sector: 0,
+                    sector_count: 40,
+                    is_audio_track: false
+                },
+                Session {
+                    start_sector: 40,
+                    sector_count: 60,
+                    is_audio_track: true
+                },
+                Session {
+                    start_sector: 100,
+                    sector_count: 50,
+                    is_audio_track: false
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn from_entries_clamped() {
+        assert_eq!(
+            Session::from_entries(&[(200, 0)], 150),
+            vec![
+                Session {
+                    start_sector: 150,
+                    sector_count: 0,
+                    is_audio_track: false
+                }
+            ]
+        );
+    }
+}
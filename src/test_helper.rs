@@ -1,8 +1,21 @@
+use futures::future::{BoxFuture, FutureExt};
+use md5::{Digest, Md5};
 use rand::Rng;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration
+};
 use tracing::trace;
 
-use crate::hasher::{HashType, MultiHasher};
+use crate::{
+    bytessource::BytesSource,
+    hasher::{HashType, MultiHasher},
+    segment::CompressionMethod
+};
 
 pub fn do_hash<RF>(
     mut reader: RF,
@@ -48,3 +61,329 @@ where
         .map(|(k, v)| (k, hex::encode(v)))
         .collect()
 }
+
+fn serve_http_request(
+    stream: TcpStream,
+    dir: &std::path::Path
+) -> std::io::Result<()>
+{
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().trim_start_matches('/');
+
+    // the query is ignored, except that fail=<file> makes requests for
+    // that file fail
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let fail = query.split('&').any(|q| q.strip_prefix("fail=") == Some(path));
+
+    // read the headers, keeping only the range
+    let mut range = None;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((k, v)) = line.split_once(':') && k.eq_ignore_ascii_case("range") {
+            range = v.trim()
+                .strip_prefix("bytes=")
+                .and_then(|r| r.split_once('-'))
+                .and_then(|(b, e)| Some((b.parse::<usize>().ok()?, e.parse::<usize>().ok()?)));
+        }
+    }
+
+    let mut stream = stream;
+
+    if fail {
+        return write!(stream, "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    }
+
+    let Ok(data) = std::fs::read(dir.join(path)) else {
+        return write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    };
+
+    match (method.as_str(), range) {
+        ("HEAD", _) => write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            data.len()
+        ),
+        ("GET", Some((beg, end))) if beg <= end && end < data.len() => {
+            write!(
+                stream,
+                "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                end + 1 - beg,
+                beg,
+                end,
+                data.len()
+            )?;
+            stream.write_all(&data[beg..=end])
+        },
+        _ => write!(stream, "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+    }
+}
+
+// Serves the files in dir over HTTP, with HEAD and range requests, on a
+// background thread; returns the port
+pub fn serve_http(dir: PathBuf) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let dir = dir.clone();
+            std::thread::spawn(move || serve_http_request(stream, &dir));
+        }
+    });
+
+    port
+}
+
+// A source of len bytes, each the low byte of its offset, which records the
+// requests made of it; each takes delay, which needs a tokio runtime if
+// nonzero
+pub struct CountingSource {
+    pub requests: Arc<Mutex<Vec<(u64, u64)>>>,
+    pub len: u64,
+    pub delay: Duration,
+    pub version: Option<String>
+}
+
+impl CountingSource {
+    pub fn new(len: u64) -> Self {
+        Self {
+            requests: Arc::new(Mutex::new(vec![])),
+            len,
+            delay: Duration::ZERO,
+            version: None
+        }
+    }
+}
+
+impl BytesSource for CountingSource {
+    fn read(
+        &self,
+        beg: u64,
+        end: u64
+    ) -> BoxFuture<'static, Result<Vec<u8>, std::io::Error>>
+    {
+        self.requests.lock().unwrap().push((beg, end));
+        let delay = self.delay;
+        async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            Ok((beg..end).map(|i| i as u8).collect())
+        }.boxed()
+    }
+
+    fn end(&self) -> u64 {
+        self.len
+    }
+
+    fn version(&self) -> Option<String> {
+        self.version.clone()
+    }
+}
+
+// How a chunk is stored in a segment made by ewf2_segment
+#[derive(Clone, Copy, Debug)]
+pub enum Ewf2Chunk {
+    // compressed with the segment's compression method
+    Compressed,
+    // stored as is, followed by its checksum
+    Checksummed,
+    // a pattern fill of its first 8 bytes, which must repeat throughout
+    Pattern
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    adler32::adler32(data).unwrap()
+}
+
+fn compress(compr_method: CompressionMethod, data: &[u8]) -> Vec<u8> {
+    match compr_method {
+        CompressionMethod::Bzip => {
+            let mut enc = bzip2::write::BzEncoder::new(
+                vec![],
+                bzip2::Compression::default()
+            );
+            enc.write_all(data).unwrap();
+            enc.finish().unwrap()
+        },
+        _ => {
+            let mut enc = flate2::write::ZlibEncoder::new(
+                vec![],
+                flate2::Compression::default()
+            );
+            enc.write_all(data).unwrap();
+            enc.finish().unwrap()
+        }
+    }
+}
+
+// Metadata sections are zlib-compressed UTF-16LE text
+fn metadata_text(text: &str) -> Vec<u8> {
+    let data = text.encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+    compress(CompressionMethod::Deflate, &data)
+}
+
+// Appends section data and the descriptor which follows it, pointing back to
+// the previous descriptor
+fn ewf2_section(
+    seg: &mut Vec<u8>,
+    prev_offset: &mut u64,
+    type_num: u32,
+    data: &[u8]
+)
+{
+    seg.extend(data);
+
+    let mut sd = vec![];
+    sd.extend(type_num.to_le_bytes());
+    // data flags
+    sd.extend(0_u32.to_le_bytes());
+    sd.extend(prev_offset.to_le_bytes());
+    sd.extend((data.len() as u64).to_le_bytes());
+    // descriptor size, padding size
+    sd.extend(64_u32.to_le_bytes());
+    sd.extend(0_u32.to_le_bytes());
+    // data integrity hash, padding
+    sd.extend([0; 28]);
+    sd.extend(adler32(&sd).to_le_bytes());
+
+    *prev_offset = seg.len() as u64;
+    seg.extend(sd);
+}
+
+// Builds a single-segment Ex01 image of data, which must be a whole number
+// of 512-byte sectors, with chunks stored as given, the (first sector,
+// sector count) acquisition errors given, and the MD5 of data
+pub fn ewf2_segment(
+    data: &[u8],
+    sectors_per_chunk: u32,
+    compr_method: CompressionMethod,
+    chunks: &[Ewf2Chunk],
+    errors: &[(u64, u32)]
+) -> Vec<u8>
+{
+    let chunk_size = sectors_per_chunk as usize * 512;
+    assert_eq!(data.len() % 512, 0);
+    assert_eq!(data.len().div_ceil(chunk_size), chunks.len());
+
+    // file header
+    let mut seg = vec![];
+    seg.extend(b"EVF2\r\n\x81\0");
+    // major and minor version
+    seg.extend([2, 1]);
+    seg.extend((compr_method as u16).to_le_bytes());
+    // segment number
+    seg.extend(1_u16.to_le_bytes());
+    // set identifier
+    seg.extend([0; 16]);
+
+    let mut prev_offset = 0;
+
+    ewf2_section(
+        &mut seg,
+        &mut prev_offset,
+        0x01,
+        &metadata_text(&format!("1\nmain\nts\tbp\n{}\t512\n\n", data.len() / 512))
+    );
+
+    ewf2_section(
+        &mut seg,
+        &mut prev_offset,
+        0x02,
+        &metadata_text(&format!("1\nmain\nsb\n{sectors_per_chunk}\n\n"))
+    );
+
+    // sector data, with table entries holding offsets into the segment
+    let sectors_offset = seg.len() as u64;
+    let mut sectors = vec![];
+    let mut entries = vec![];
+
+    for (c, kind) in data.chunks(chunk_size).zip(chunks) {
+        let offset = sectors_offset + sectors.len() as u64;
+
+        let (offset, size, flags) = match kind {
+            Ewf2Chunk::Compressed => {
+                let z = compress(compr_method, c);
+                sectors.extend(&z);
+                (offset, z.len(), 0x01)
+            },
+            Ewf2Chunk::Checksummed => {
+                sectors.extend(c);
+                sectors.extend(adler32(c).to_le_bytes());
+                (offset, c.len() + 4, 0x02)
+            },
+            Ewf2Chunk::Pattern => (
+                u64::from_le_bytes(c[..8].try_into().unwrap()),
+                0,
+                0x04
+            )
+        };
+
+        entries.extend(offset.to_le_bytes());
+        entries.extend((size as u32).to_le_bytes());
+        entries.extend((flags as u32).to_le_bytes());
+    }
+
+    ewf2_section(&mut seg, &mut prev_offset, 0x03, &sectors);
+
+    // sector table: header, entries, footer
+    let mut table = vec![];
+    // first chunk number
+    table.extend(0_u64.to_le_bytes());
+    table.extend((chunks.len() as u32).to_le_bytes());
+    table.extend([0; 4]);
+    table.extend(adler32(&table).to_le_bytes());
+    table.extend([0; 12]);
+    table.extend(&entries);
+    table.extend(adler32(&entries).to_le_bytes());
+    table.extend([0; 12]);
+
+    ewf2_section(&mut seg, &mut prev_offset, 0x04, &table);
+
+    // error table: header, entries, footer
+    if !errors.is_empty() {
+        let mut table = vec![];
+        table.extend((errors.len() as u32).to_le_bytes());
+        table.extend([0; 12]);
+        table.extend(adler32(&table).to_le_bytes());
+        table.extend([0; 12]);
+
+        let mut entries = vec![];
+        for (first, count) in errors {
+            entries.extend(first.to_le_bytes());
+            entries.extend(count.to_le_bytes());
+            entries.extend([0; 4]);
+        }
+        table.extend(&entries);
+        table.extend(adler32(&entries).to_le_bytes());
+        table.extend([0; 12]);
+
+        ewf2_section(&mut seg, &mut prev_offset, 0x05, &table);
+    }
+
+    // MD5 hash
+    let mut hash = Md5::digest(data).to_vec();
+    hash.extend(adler32(&hash).to_le_bytes());
+    hash.extend([0; 12]);
+
+    ewf2_section(&mut seg, &mut prev_offset, 0x08, &hash);
+
+    // done
+    ewf2_section(&mut seg, &mut prev_offset, 0x0f, &[]);
+
+    seg
+}
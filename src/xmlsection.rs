@@ -0,0 +1,122 @@
+// xheader and xhash sections contain a single root element whose children
+// are simple elements holding only text, e.g.,
+//
+// <?xml version="1.0" encoding="UTF-8"?>
+// <xhash>
+//   <md5>...</md5>
+//   <sha1>...</sha1>
+// </xhash>
+//
+// so we don't need a general XML parser to read them.
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn skip_prolog(mut s: &str) -> &str {
+    loop {
+        s = s.trim_start_matches('\u{feff}').trim_start();
+        if s.starts_with("<?") {
+            s = s.find("?>").map(|i| &s[i + 2..]).unwrap_or("");
+        }
+        else if s.starts_with("<!--") {
+            s = s.find("-->").map(|i| &s[i + 3..]).unwrap_or("");
+        }
+        else {
+            return s;
+        }
+    }
+}
+
+pub fn parse_flat_xml(
+    text: &str,
+    root: &str
+) -> Result<Vec<(String, String)>, String>
+{
+    let s = skip_prolog(text);
+
+    let open = format!("<{root}>");
+    let close = format!("</{root}>");
+
+    let mut s = s.strip_prefix(&open)
+        .ok_or_else(|| format!("missing {open}"))?;
+
+    let mut elements = vec![];
+
+    loop {
+        s = skip_prolog(s);
+
+        if s.starts_with(&close) {
+            return Ok(elements);
+        }
+
+        let tag_end = s.strip_prefix('<')
+            .and_then(|t| t.find('>'))
+            .ok_or_else(|| format!("missing {close}"))?;
+
+        let tag = &s[1..tag_end + 1];
+        s = &s[tag_end + 2..];
+
+        if let Some(tag) = tag.strip_suffix('/') {
+            // empty element
+            let name = tag.split_whitespace().next().unwrap_or("");
+            elements.push((name.to_string(), String::new()));
+            continue;
+        }
+
+        // drop any attributes
+        let name = tag.split_whitespace().next().unwrap_or("");
+
+        let end_tag = format!("</{name}>");
+        let value_end = s.find(&end_tag)
+            .ok_or_else(|| format!("missing {end_tag}"))?;
+
+        elements.push((name.to_string(), unescape(s[..value_end].trim())));
+
+        s = &s[value_end + end_tag.len()..];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_flat_xml_ok() {
+        let text = "\u{feff}<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<xhash>\n\t<md5>d41d8cd98f00b204e9800998ecf8427e</md5>\n\t<!-- comment -->\n\t<note a=\"b\">x &amp; y</note>\n\t<empty/>\n</xhash>\n\n";
+
+        assert_eq!(
+            parse_flat_xml(text, "xhash").unwrap(),
+            vec![
+                ("md5".into(), "d41d8cd98f00b204e9800998ecf8427e".into()),
+                ("note".into(), "x & y".into()),
+                ("empty".into(), "".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_flat_xml_wrong_root() {
+        assert_eq!(
+            parse_flat_xml("<xheader></xheader>", "xhash").unwrap_err(),
+            "missing <xhash>"
+        );
+    }
+
+    #[test]
+    fn parse_flat_xml_unclosed() {
+        assert_eq!(
+            parse_flat_xml("<xhash><md5>00</md5>", "xhash").unwrap_err(),
+            "missing </xhash>"
+        );
+
+        assert_eq!(
+            parse_flat_xml("<xhash><md5>00</xhash>", "xhash").unwrap_err(),
+            "missing </md5>"
+        );
+    }
+}